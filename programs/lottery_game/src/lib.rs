@@ -0,0 +1,3649 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{
+    self, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use solana_program::hash::{hash, hashv};
+use solana_program::sysvar::slot_hashes;
+use solana_program::{pubkey, pubkey::Pubkey};
+
+// This is your program's public key and it will update
+// automatically when you build the project.
+declare_id!("FR87ZA1sCVabEMe21X24WkaBqqWdMne18UzGgKdQb4pE");
+
+pub const GAME_PREFIX: &str = "game";
+pub const PALYER_PREFIX: &str = "player";
+pub const SOLANA_ADDRESS: Pubkey = pubkey!("So11111111111111111111111111111111111111111");
+pub const LAMPORT_PER_SOL: u64 = 1000000000;
+pub const BOX_PREFIX: &str = "box";
+pub const VAULT_PREFIX: &str = "vault";
+pub const PROFILE_PREFIX: &str = "profile";
+pub const BATCH_PREFIX: &str = "batch";
+pub const MAX_IDENTIFIER_LEN: usize = 32;
+pub const MAX_PROFILE_BOXES: usize = 16;
+pub const MAX_PROFILE_MINTS: usize = 4;
+pub const MAX_BATCH_SIZE: usize = 10;
+pub const MAX_PRIZES: usize = 10;
+pub const GAME_VERSION: u8 = 4; // bump, with a migrate_game arm, whenever Game's layout changes
+pub const GAME_SPACE: usize = 400;
+pub const PLAYER_SPACE: usize = 430;
+pub const LEGACY_PLAYER_SPACE: usize = 100;
+// 8 + 105 fixed + 4 + MAX_PROFILE_MINTS * 56 + 4 + MAX_PROFILE_BOXES * 9 = 489
+pub const PROFILE_SPACE: usize = 500;
+// 8 + 205 fixed + 4 + MAX_BATCH_SIZE indices + 176 prize table = 403
+pub const BATCH_SPACE: usize = 420;
+pub const MAX_OPERATORS: usize = 5;
+pub const WITHDRAWAL_PREFIX: &str = "withdrawal";
+pub const TREASURY_CHANGE_PREFIX: &str = "treasury_change";
+pub const DEFAULT_WITHDRAWAL_DELAY: i64 = 24 * 60 * 60; // seconds
+pub const BPS_DENOMINATOR: u16 = 10_000;
+pub const TIMELOCK_WINDOW: i64 = 3 * 24 * 60 * 60; // seconds a queued action stays executable
+pub const PAUSE_PLAYS: u8 = 1 << 0;
+pub const PAUSE_CLAIMS: u8 = 1 << 1;
+pub const PAUSE_WITHDRAWALS: u8 = 1 << 2;
+pub const PLAY_EXPIRY_SLOTS: u64 = 512; // depth of the SlotHashes sysvar
+
+#[program]
+mod lottery_game {
+    use super::*;
+    pub fn init_game(ctx: Context<InitGameCtx>, ix: InitGameIx) -> Result<()> {
+        let bump = ctx.bumps.game;
+
+        let new_game = Game {
+            bump,
+            authority: ctx.accounts.payer.key(),
+            creator: ctx.accounts.payer.key(),
+            pending_authority: Pubkey::default(),
+            randomness_source: RandomnessSource::CommitReveal,
+            oracle: Pubkey::default(),
+            sol_liabilities: 0,
+            paused: 0,
+            created_at: Clock::get().unwrap().unix_timestamp,
+            version: GAME_VERSION,
+            treasurer: ctx.accounts.payer.key(),
+            operators: Vec::new(),
+            withdrawal_delay: DEFAULT_WITHDRAWAL_DELAY,
+            open_deposits: false,
+            total_sol_deposited: ix.amount,
+            game_id: ix.game_id,
+            instant_prize_limit: 0,
+            operator_max_rtp_bps: 0,
+        };
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.game.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, ix.amount)?;
+
+        let game = &mut ctx.accounts.game;
+
+        game.set_inner(new_game);
+
+        emit!(GameInitialized {
+            game: game.key(),
+            creator: game.creator,
+            game_id: game.game_id,
+            amount: ix.amount,
+        });
+        Ok(())
+    }
+
+    // Rewrites a game created before token settings moved to TokenVault, the
+    // only layout deployed before games carried a version, or a version 3 game.
+    // A legacy game has to register its mint again with `add_token`, which
+    // reads the decimals from the mint instead of the stored default_multiplier.
+    pub fn migrate_game(ctx: Context<MigrateGameCtx>) -> Result<()> {
+        let game_info = ctx.accounts.game.to_account_info();
+
+        if game_info.try_borrow_data()?[..8] != Game::DISCRIMINATOR {
+            return err!(ErrorCode::InvalidGame);
+        }
+
+        if game_info.data_len() < GAME_SPACE {
+            let rent = Rent::get()?.minimum_balance(GAME_SPACE);
+            let lamports = game_info.lamports();
+            if lamports < rent {
+                let cpi_context = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.payer.to_account_info(),
+                        to: game_info.clone(),
+                    },
+                );
+                system_program::transfer(cpi_context, rent - lamports)?;
+            }
+            game_info.realloc(GAME_SPACE, false)?;
+        }
+
+        let game = {
+            let data = game_info.try_borrow_data()?;
+
+            match Game::try_deserialize(&mut &data[..]) {
+                Ok(game) if game.version == GAME_VERSION => {
+                    return err!(ErrorCode::AlreadyMigrated);
+                }
+                // Version 3 ended before operator_max_rtp_bps. The bytes it
+                // decodes from may be left over from a longer operators list.
+                Ok(game) if game.version == 3 => Game {
+                    version: GAME_VERSION,
+                    operator_max_rtp_bps: 0,
+                    ..game
+                },
+                _ => {
+                    let legacy = LegacyGame::deserialize(&mut &data[8..])?;
+                    if legacy.token_address != Pubkey::default() {
+                        msg!("re-register mint {} with add_token", legacy.token_address);
+                    }
+
+                    Game {
+                        bump: legacy.bump,
+                        authority: legacy.authority,
+                        creator: legacy.authority,
+                        pending_authority: Pubkey::default(),
+                        randomness_source: RandomnessSource::CommitReveal,
+                        oracle: Pubkey::default(),
+                        sol_liabilities: 0,
+                        paused: 0,
+                        created_at: legacy.created_at,
+                        version: GAME_VERSION,
+                        treasurer: legacy.authority,
+                        operators: Vec::new(),
+                        withdrawal_delay: DEFAULT_WITHDRAWAL_DELAY,
+                        open_deposits: false,
+                        total_sol_deposited: 0,
+                        game_id: 0,
+                        instant_prize_limit: 0,
+                        operator_max_rtp_bps: 0,
+                    }
+                }
+            }
+        };
+
+        let mut data = game_info.try_borrow_mut_data()?;
+        game.try_serialize(&mut &mut data[..])?;
+
+        emit!(GameMigrated {
+            game: game_info.key(),
+            version: game.version,
+        });
+
+        Ok(())
+    }
+
+    // Rewrites a Player created before records were tied to a game, so the
+    // usual claim and close instructions accept it. Legacy records do not say
+    // which game they paid into, so the winner or the owner of a game that
+    // already existed when the record was created attaches it, and only while
+    // that game can still cover the win.
+    pub fn migrate_player(ctx: Context<MigratePlayerCtx>) -> Result<()> {
+        let player_info = ctx.accounts.player.to_account_info();
+
+        if player_info.try_borrow_data()?[..8] != Player::DISCRIMINATOR {
+            return err!(ErrorCode::InvalidPlayer);
+        }
+        if player_info.data_len() != LEGACY_PLAYER_SPACE {
+            return err!(ErrorCode::AlreadyMigrated);
+        }
+
+        let legacy = LegacyPlayer::deserialize(&mut &player_info.try_borrow_data()?[8..])?;
+
+        // Legacy plays created their record at the identifier's address.
+        let address = Pubkey::create_program_address(
+            &[
+                PALYER_PREFIX.as_bytes(),
+                legacy.identifier.as_bytes(),
+                &[legacy.bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| ErrorCode::InvalidPlayer)?;
+        if address != player_info.key() {
+            return err!(ErrorCode::InvalidPlayer);
+        }
+
+        let payer = ctx.accounts.payer.key();
+        if payer != legacy.authority && payer != ctx.accounts.game.authority {
+            return err!(ErrorCode::InvalidPlayer);
+        }
+        if legacy.created_at < ctx.accounts.game.created_at {
+            return err!(ErrorCode::InvalidGame);
+        }
+
+        let win_amount = if legacy.claimed { 0 } else { legacy.win_amount };
+        let (currency, mint) = if legacy.token_type == "sol" {
+            if win_amount > sol_surplus(&ctx.accounts.game)? {
+                return err!(ErrorCode::InsufficientBankroll);
+            }
+            add_liability(&mut ctx.accounts.game.sol_liabilities, win_amount)?;
+            (Currency::Sol, Pubkey::default())
+        } else {
+            let (Some(token_vault), Some(game_token_account)) = (
+                ctx.accounts.token_vault.as_deref_mut(),
+                ctx.accounts.game_token_account.as_ref(),
+            ) else {
+                return err!(ErrorCode::MissingTokenVault);
+            };
+            if game_token_account.mint != token_vault.mint {
+                return err!(ErrorCode::InvalidMint);
+            }
+            let surplus = game_token_account
+                .amount
+                .saturating_sub(token_vault.liabilities);
+            if win_amount > surplus {
+                return err!(ErrorCode::InsufficientBankroll);
+            }
+            add_liability(&mut token_vault.liabilities, win_amount)?;
+            (Currency::Token, token_vault.mint)
+        };
+
+        let rent = Rent::get()?.minimum_balance(PLAYER_SPACE);
+        let lamports = player_info.lamports();
+        if lamports < rent {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: player_info.clone(),
+                },
+            );
+            system_program::transfer(cpi_context, rent - lamports)?;
+        }
+        player_info.realloc(PLAYER_SPACE, false)?;
+
+        let player = Player {
+            bump: legacy.bump,
+            authority: legacy.authority,
+            game: ctx.accounts.game.key(),
+            status: if legacy.claimed {
+                PlayerStatus::Claimed
+            } else {
+                PlayerStatus::Revealed
+            },
+            randomness_source: RandomnessSource::CommitReveal,
+            created_at: legacy.created_at,
+            win_amount,
+            wager: 0,
+            reserved: win_amount,
+            currency,
+            mint,
+            identifier: legacy.identifier,
+            box_config: Pubkey::default(),
+            commitment: [0; 32],
+            commit_slot: 0,
+            prizes: PrizeTable::default(),
+            auto_claim: false,
+        };
+
+        let mut data = player_info.try_borrow_mut_data()?;
+        player.try_serialize(&mut &mut data[..])?;
+
+        emit!(PlayerMigrated {
+            game: player.game,
+            player: player.authority,
+            play: player_info.key(),
+            currency: player.currency,
+            mint: player.mint,
+            win_amount: player.win_amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn propose_authority(
+        ctx: Context<ProposeAuthorityCtx>,
+        ix: ProposeAuthorityIx,
+    ) -> Result<()> {
+        ctx.accounts.game.pending_authority = ix.new_authority;
+
+        emit!(AuthorityProposed {
+            game: ctx.accounts.game.key(),
+            pending_authority: ix.new_authority,
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthorityCtx>) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        game.authority = game.pending_authority;
+        game.pending_authority = Pubkey::default();
+
+        emit!(AuthorityTransferred {
+            game: game.key(),
+            authority: game.authority,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_operator(ctx: Context<ManageRolesCtx>, ix: OperatorIx) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        if game.operators.contains(&ix.operator) {
+            return err!(ErrorCode::OperatorAlreadyAdded);
+        }
+        if game.operators.len() >= MAX_OPERATORS {
+            return err!(ErrorCode::TooManyOperators);
+        }
+        game.operators.push(ix.operator);
+
+        emit!(OperatorAdded {
+            game: game.key(),
+            operator: ix.operator,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_operator(ctx: Context<ManageRolesCtx>, ix: OperatorIx) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        let index = game
+            .operators
+            .iter()
+            .position(|operator| *operator == ix.operator)
+            .ok_or(ErrorCode::OperatorNotFound)?;
+        game.operators.remove(index);
+
+        emit!(OperatorRemoved {
+            game: game.key(),
+            operator: ix.operator,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_operator_rtp_limit(
+        ctx: Context<ManageRolesCtx>,
+        ix: OperatorRtpLimitIx,
+    ) -> Result<()> {
+        if ix.max_rtp_bps > BPS_DENOMINATOR {
+            return err!(ErrorCode::InvalidRtpLimit);
+        }
+        ctx.accounts.game.operator_max_rtp_bps = ix.max_rtp_bps;
+
+        emit!(OperatorRtpLimitChanged {
+            game: ctx.accounts.game.key(),
+            max_rtp_bps: ix.max_rtp_bps,
+        });
+
+        Ok(())
+    }
+
+    // The treasurer and the withdrawal delay change only after the current
+    // delay, so the owner cannot shorten it and withdraw in the same breath.
+    pub fn queue_treasury_change(
+        ctx: Context<QueueTreasuryChangeCtx>,
+        ix: TreasuryChangeIx,
+    ) -> Result<()> {
+        if ix.withdrawal_delay < 0 {
+            return err!(ErrorCode::InvalidWithdrawalDelay);
+        }
+        if ix.treasurer == Pubkey::default() {
+            return err!(ErrorCode::InvalidTreasurer);
+        }
+        let now = Clock::get()?.unix_timestamp;
+        let unlock_at = now
+            .checked_add(ctx.accounts.game.withdrawal_delay)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let treasury_change = &mut ctx.accounts.treasury_change;
+        treasury_change.bump = ctx.bumps.treasury_change;
+        treasury_change.game = ctx.accounts.game.key();
+        treasury_change.treasurer = ix.treasurer;
+        treasury_change.withdrawal_delay = ix.withdrawal_delay;
+        treasury_change.unlock_at = unlock_at;
+        treasury_change.created_at = now;
+
+        emit!(TreasuryChangeQueued {
+            game: treasury_change.game,
+            treasurer: ix.treasurer,
+            withdrawal_delay: ix.withdrawal_delay,
+            unlock_at,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_treasury_change(ctx: Context<CancelTreasuryChangeCtx>) -> Result<()> {
+        emit!(TreasuryChangeCancelled {
+            game: ctx.accounts.game.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn apply_treasury_change(ctx: Context<ApplyTreasuryChangeCtx>) -> Result<()> {
+        let treasury_change = &ctx.accounts.treasury_change;
+        check_unlocked(treasury_change.unlock_at)?;
+
+        let game = &mut ctx.accounts.game;
+        game.treasurer = treasury_change.treasurer;
+        game.withdrawal_delay = treasury_change.withdrawal_delay;
+
+        emit!(TreasuryChanged {
+            game: game.key(),
+            treasurer: game.treasurer,
+            withdrawal_delay: game.withdrawal_delay,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_pause(ctx: Context<SetPauseCtx>, ix: SetPauseIx) -> Result<()> {
+        if ix.paused & !(PAUSE_PLAYS | PAUSE_CLAIMS | PAUSE_WITHDRAWALS) != 0 {
+            return err!(ErrorCode::InvalidPauseFlags);
+        }
+        ctx.accounts.game.paused = ix.paused;
+
+        emit!(PauseChanged {
+            game: ctx.accounts.game.key(),
+            paused: ix.paused,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_open_deposits(
+        ctx: Context<SetOpenDepositsCtx>,
+        ix: SetOpenDepositsIx,
+    ) -> Result<()> {
+        ctx.accounts.game.open_deposits = ix.open;
+
+        emit!(OpenDepositsChanged {
+            game: ctx.accounts.game.key(),
+            open: ix.open,
+        });
+
+        Ok(())
+    }
+
+    pub fn add_token(ctx: Context<AddTokenCtx>, ix: AddTokenIx) -> Result<()> {
+        if ix.default_multiplier != ctx.accounts.mint.decimals {
+            return err!(ErrorCode::InvalidDecimals);
+        }
+        let received = transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payer_token_account,
+            &mut ctx.accounts.game_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.payer,
+            ix.amount,
+        )?;
+
+        let token_vault = &mut ctx.accounts.token_vault;
+        if token_vault.mint != Pubkey::default() {
+            return err!(ErrorCode::TokenAlreadyAdded);
+        }
+        token_vault.bump = ctx.bumps.token_vault;
+        token_vault.game = ctx.accounts.game.key();
+        token_vault.mint = ctx.accounts.mint.key();
+        token_vault.decimals = ctx.accounts.mint.decimals;
+        token_vault.enabled = true;
+        token_vault.liabilities = 0;
+        token_vault.total_deposited = received;
+        token_vault.instant_prize_limit = 0;
+
+        emit!(TokenAdded {
+            game: token_vault.game,
+            mint: token_vault.mint,
+            decimals: token_vault.decimals,
+            amount: received,
+        });
+
+        Ok(())
+    }
+
+    pub fn disable_token(ctx: Context<DisableTokenCtx>) -> Result<()> {
+        ctx.accounts.token_vault.enabled = false;
+
+        emit!(TokenDisabled {
+            game: ctx.accounts.game.key(),
+            mint: ctx.accounts.token_vault.mint,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_token(ctx: Context<RemoveTokenCtx>) -> Result<()> {
+        let creator = ctx.accounts.game.creator;
+        let game_id = game_id_seed(ctx.accounts.game.game_id);
+
+        let game_seeds = &[
+            GAME_PREFIX.as_bytes(),
+            creator.as_ref(),
+            &game_id,
+            &[ctx.accounts.game.bump],
+        ];
+
+        let game_signer_seeds = &[&game_seeds[..]];
+
+        let accounts = CloseAccount {
+            account: ctx.accounts.game_token_account.to_account_info(),
+            destination: ctx.accounts.payer.to_account_info(),
+            authority: ctx.accounts.game.to_account_info(),
+        };
+
+        let tx_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            accounts,
+            game_signer_seeds,
+        );
+
+        token_interface::close_account(tx_ctx)?;
+
+        emit!(TokenRemoved {
+            game: ctx.accounts.game.key(),
+            mint: ctx.accounts.mint.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit_sol(ctx: Context<DepositSolCtx>, ix: DepositIx) -> Result<()> {
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.game.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi_context, ix.amount)?;
+
+        let game = &mut ctx.accounts.game;
+        game.total_sol_deposited = game
+            .total_sol_deposited
+            .checked_add(ix.amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(Deposited {
+            game: game.key(),
+            depositor: ctx.accounts.payer.key(),
+            currency: Currency::Sol,
+            mint: Pubkey::default(),
+            amount: ix.amount,
+        });
+
+        Ok(())
+    }
+
+    pub fn deposit_token(ctx: Context<DepositTokenCtx>, ix: DepositIx) -> Result<()> {
+        let received = transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payer_token_account,
+            &mut ctx.accounts.game_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.payer,
+            ix.amount,
+        )?;
+
+        let token_vault = &mut ctx.accounts.token_vault;
+        token_vault.total_deposited = token_vault
+            .total_deposited
+            .checked_add(received)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        emit!(Deposited {
+            game: token_vault.game,
+            depositor: ctx.accounts.payer.key(),
+            currency: Currency::Token,
+            mint: token_vault.mint,
+            amount: received,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_instant_prize_limit(
+        ctx: Context<SetInstantPrizeLimitCtx>,
+        ix: SetInstantPrizeLimitIx,
+    ) -> Result<()> {
+        let (currency, mint) = match ctx.accounts.token_vault.as_deref_mut() {
+            Some(token_vault) => {
+                token_vault.instant_prize_limit = ix.limit;
+                (Currency::Token, token_vault.mint)
+            }
+            None => {
+                ctx.accounts.game.instant_prize_limit = ix.limit;
+                (Currency::Sol, Pubkey::default())
+            }
+        };
+
+        emit!(InstantPrizeLimitChanged {
+            game: ctx.accounts.game.key(),
+            currency,
+            mint,
+            limit: ix.limit,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_randomness_source(
+        ctx: Context<SetRandomnessSourceCtx>,
+        ix: SetRandomnessSourceIx,
+    ) -> Result<()> {
+        let game = &mut ctx.accounts.game;
+        if ix.source == RandomnessSource::Oracle && ix.oracle == Pubkey::default() {
+            return err!(ErrorCode::InvalidOracle);
+        }
+        game.randomness_source = ix.source;
+        game.oracle = ix.oracle;
+
+        emit!(RandomnessSourceChanged {
+            game: game.key(),
+            source: ix.source,
+            oracle: ix.oracle,
+        });
+
+        Ok(())
+    }
+
+    pub fn create_box(ctx: Context<CreateBoxCtx>, ix: CreateBoxIx) -> Result<()> {
+        validate_prize_table(ix.denominator, &ix.chances, &ix.win_values)?;
+        if ctx.accounts.game.authority != ctx.accounts.payer.key() {
+            check_operator_rtp(
+                ctx.accounts.game.operator_rtp_limit(),
+                ix.price,
+                ix.denominator,
+                &ix.chances,
+                &ix.win_values,
+            )?;
+        }
+        if (ix.currency == Currency::Sol) != (ix.mint == Pubkey::default()) {
+            return err!(ErrorCode::InvalidMint);
+        }
+
+        let box_config = &mut ctx.accounts.box_config;
+        box_config.bump = ctx.bumps.box_config;
+        box_config.game = ctx.accounts.game.key();
+        box_config.box_id = ix.box_id;
+        box_config.currency = ix.currency;
+        box_config.mint = ix.mint;
+        box_config.price = ix.price;
+        box_config.denominator = ix.denominator;
+        box_config.chances = ix.chances;
+        box_config.win_values = ix.win_values;
+        box_config.retired = false;
+        box_config.emit_configured();
+
+        Ok(())
+    }
+
+    pub fn update_box(ctx: Context<UpdateBoxCtx>, ix: UpdateBoxIx) -> Result<()> {
+        validate_prize_table(ix.denominator, &ix.chances, &ix.win_values)?;
+        if ctx.accounts.game.authority != ctx.accounts.payer.key() {
+            check_operator_rtp(
+                ctx.accounts.game.operator_rtp_limit(),
+                ix.price,
+                ix.denominator,
+                &ix.chances,
+                &ix.win_values,
+            )?;
+        }
+
+        let box_config = &mut ctx.accounts.box_config;
+        box_config.price = ix.price;
+        box_config.denominator = ix.denominator;
+        box_config.chances = ix.chances;
+        box_config.win_values = ix.win_values;
+        box_config.emit_configured();
+
+        Ok(())
+    }
+
+    pub fn retire_box(ctx: Context<UpdateBoxCtx>) -> Result<()> {
+        let box_config = &mut ctx.accounts.box_config;
+        box_config.retired = true;
+
+        emit!(BoxRetired {
+            game: box_config.game,
+            box_config: box_config.key(),
+            box_id: box_config.box_id,
+        });
+
+        Ok(())
+    }
+
+    pub fn play_sol(ctx: Context<PlaySolCtx>, ix: PlayIx) -> Result<()> {
+        let box_config =
+            load_box_config(&ctx.accounts.box_config, Currency::Sol, Pubkey::default())?;
+        if ix.identifier.len() > MAX_IDENTIFIER_LEN {
+            return err!(ErrorCode::IdentifierTooLong);
+        }
+        if ix.auto_claim {
+            check_auto_claim(&ctx.accounts.game)?;
+        }
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.game.to_account_info(),
+            },
+        );
+
+        system_program::transfer(cpi_context, box_config.price)?;
+
+        let prizes = box_config.prize_table();
+        let reserve = prizes.max_prize().max(box_config.price);
+        if reserve > sol_surplus(&ctx.accounts.game)? {
+            return err!(ErrorCode::InsufficientBankroll);
+        }
+
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(
+            ctx.bumps.player_profile,
+            ctx.accounts.game.key(),
+            ctx.accounts.payer.key(),
+        );
+        player_profile.record_play(
+            Currency::Sol,
+            Pubkey::default(),
+            box_config.box_id,
+            1,
+            box_config.price,
+        )?;
+
+        add_liability(&mut ctx.accounts.game.sol_liabilities, reserve)?;
+
+        let clock = Clock::get()?;
+        let player = &mut ctx.accounts.player;
+        player.bump = ctx.bumps.player;
+        player.authority = ctx.accounts.payer.key();
+        player.game = ctx.accounts.game.key();
+        player.identifier = ix.identifier;
+        player.box_config = ctx.accounts.box_config.key();
+        player.win_amount = 0;
+        player.wager = box_config.price;
+        player.reserved = reserve;
+        player.prizes = prizes;
+        player.currency = Currency::Sol;
+        player.mint = Pubkey::default();
+        player.status = PlayerStatus::Pending;
+        player.randomness_source = ctx.accounts.game.randomness_source;
+        player.commitment = ix.commitment;
+        player.commit_slot = clock.slot;
+        player.auto_claim = ix.auto_claim;
+        player.created_at = clock.unix_timestamp;
+
+        emit!(PlayStarted {
+            game: player.game,
+            player: player.authority,
+            play: player.key(),
+            box_id: box_config.box_id,
+            currency: player.currency,
+            mint: player.mint,
+            count: 1,
+            wager: player.wager,
+            reserved: player.reserved,
+        });
+
+        Ok(())
+    }
+
+    pub fn play_token(ctx: Context<PlayTokenCtx>, ix: PlayIx) -> Result<()> {
+        let box_config = load_box_config(
+            &ctx.accounts.box_config,
+            Currency::Token,
+            ctx.accounts.mint.key(),
+        )?;
+        if ix.identifier.len() > MAX_IDENTIFIER_LEN {
+            return err!(ErrorCode::IdentifierTooLong);
+        }
+        if ix.auto_claim {
+            check_auto_claim(&ctx.accounts.game)?;
+        }
+
+        let wager = transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payer_token_account,
+            &mut ctx.accounts.game_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.payer,
+            box_config.price,
+        )?;
+        let prizes = box_config.prize_table();
+        let reserve = prizes.max_prize().max(wager);
+        let surplus = ctx
+            .accounts
+            .game_token_account
+            .amount
+            .saturating_sub(ctx.accounts.token_vault.liabilities);
+        if reserve > surplus {
+            return err!(ErrorCode::InsufficientBankroll);
+        }
+
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(
+            ctx.bumps.player_profile,
+            ctx.accounts.game.key(),
+            ctx.accounts.payer.key(),
+        );
+        player_profile.record_play(
+            Currency::Token,
+            ctx.accounts.mint.key(),
+            box_config.box_id,
+            1,
+            wager,
+        )?;
+
+        add_liability(&mut ctx.accounts.token_vault.liabilities, reserve)?;
+
+        let clock = Clock::get()?;
+        let player = &mut ctx.accounts.player;
+        player.bump = ctx.bumps.player;
+        player.authority = ctx.accounts.payer.key();
+        player.game = ctx.accounts.game.key();
+        player.identifier = ix.identifier;
+        player.box_config = ctx.accounts.box_config.key();
+        player.win_amount = 0;
+        player.wager = wager;
+        player.reserved = reserve;
+        player.prizes = prizes;
+        player.currency = Currency::Token;
+        player.mint = ctx.accounts.mint.key();
+        player.status = PlayerStatus::Pending;
+        player.randomness_source = ctx.accounts.game.randomness_source;
+        player.commitment = ix.commitment;
+        player.commit_slot = clock.slot;
+        player.auto_claim = ix.auto_claim;
+        player.created_at = clock.unix_timestamp;
+
+        emit!(PlayStarted {
+            game: player.game,
+            player: player.authority,
+            play: player.key(),
+            box_id: box_config.box_id,
+            currency: player.currency,
+            mint: player.mint,
+            count: 1,
+            wager: player.wager,
+            reserved: player.reserved,
+        });
+
+        Ok(())
+    }
+
+    pub fn reveal(ctx: Context<RevealCtx>, ix: RevealIx) -> Result<()> {
+        let player = &mut ctx.accounts.player;
+
+        if hash(&ix.seed).to_bytes() != player.commitment {
+            return err!(ErrorCode::InvalidReveal);
+        }
+
+        let slot_hash = find_slot_hash(&ctx.accounts.slot_hashes, player.commit_slot)?;
+        let randomness = hashv(&[&ix.seed, &slot_hash, player.key().as_ref()]).to_bytes();
+        player.settle(
+            &mut ctx.accounts.game,
+            ctx.accounts.token_vault.as_deref_mut(),
+            &ctx.accounts.box_config,
+            &randomness,
+        )?;
+
+        Ok(())
+    }
+
+    // The registered oracle is trusted: its randomness is used as delivered and
+    // no VRF proof is verified here. Only register an oracle program that
+    // checks the proof itself before calling back.
+    pub fn fulfill_randomness(
+        ctx: Context<FulfillRandomnessCtx>,
+        ix: FulfillRandomnessIx,
+    ) -> Result<()> {
+        let player = &mut ctx.accounts.player;
+        let randomness = hashv(&[&ix.randomness, player.key().as_ref()]).to_bytes();
+        player.settle(
+            &mut ctx.accounts.game,
+            ctx.accounts.token_vault.as_deref_mut(),
+            &ctx.accounts.box_config,
+            &randomness,
+        )?;
+
+        // Auto-claim plays are paid here, in the oracle's transaction, so the
+        // player cannot see the outcome before it is final. Whatever the
+        // callback cannot pay is left for the usual claim.
+        let Some(authority) = ctx.accounts.authority.as_ref() else {
+            return Ok(());
+        };
+        if !player.auto_claim || ctx.accounts.game.paused & PAUSE_CLAIMS != 0 {
+            return Ok(());
+        }
+        let player_profile = ctx.accounts.player_profile.as_deref_mut();
+
+        match player.currency {
+            Currency::Sol => {
+                if player.win_amount > ctx.accounts.game.instant_prize_limit {
+                    return Ok(());
+                }
+                let amount = player
+                    .stake()
+                    .claim(&mut ctx.accounts.game.sol_liabilities, player_profile)?;
+                ctx.accounts.game.sub_lamports(amount)?;
+                authority.add_lamports(amount)?;
+            }
+            Currency::Token => {
+                let (
+                    Some(token_vault),
+                    Some(game_token_account),
+                    Some(authority_token_account),
+                    Some(mint),
+                    Some(token_program),
+                ) = (
+                    ctx.accounts.token_vault.as_deref_mut(),
+                    ctx.accounts.game_token_account.as_ref(),
+                    ctx.accounts.authority_token_account.as_ref(),
+                    ctx.accounts.mint.as_ref(),
+                    ctx.accounts.token_program.as_ref(),
+                )
+                else {
+                    return Ok(());
+                };
+                if player.win_amount > token_vault.instant_prize_limit {
+                    return Ok(());
+                }
+                let amount = player
+                    .stake()
+                    .claim(&mut token_vault.liabilities, player_profile)?;
+                transfer_out(
+                    &ctx.accounts.game,
+                    token_program,
+                    game_token_account,
+                    authority_token_account,
+                    mint,
+                    amount,
+                )?;
+            }
+        }
+
+        player.close(authority.to_account_info())?;
+
+        Ok(())
+    }
+
+    pub fn claim_reward_sol(ctx: Context<ClaimRewardSolCtx>) -> Result<()> {
+        let player = &mut ctx.accounts.player;
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(ctx.bumps.player_profile, player.game, player.authority);
+
+        let amount = player.stake().claim(
+            &mut ctx.accounts.game.sol_liabilities,
+            Some(&mut **player_profile),
+        )?;
+        ctx.accounts.game.sub_lamports(amount)?;
+        ctx.accounts.payer.add_lamports(amount)?;
+
+        Ok(())
+    }
+
+    pub fn claim_reward_token(ctx: Context<ClaimRewardTokenCtx>) -> Result<()> {
+        let player = &mut ctx.accounts.player;
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(ctx.bumps.player_profile, player.game, player.authority);
+
+        let amount = player.stake().claim(
+            &mut ctx.accounts.token_vault.liabilities,
+            Some(&mut **player_profile),
+        )?;
+        transfer_out(
+            &ctx.accounts.game,
+            &ctx.accounts.token_program,
+            &ctx.accounts.game_token_account,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.mint,
+            amount,
+        )
+    }
+
+    pub fn close_player(ctx: Context<ClosePlayerCtx>) -> Result<()> {
+        let player = &ctx.accounts.player;
+
+        emit!(PlayClosed {
+            game: player.game,
+            player: player.authority,
+            play: player.key(),
+        });
+
+        Ok(())
+    }
+
+    // Anyone may expire a stale play, so the house can release reservations
+    // that a player never settles. The record still belongs to its authority,
+    // who claims the refund or closes it.
+    pub fn expire_play(ctx: Context<ExpirePlayCtx>) -> Result<()> {
+        ctx.accounts.player.stake().expire(
+            &mut ctx.accounts.game,
+            ctx.accounts.token_vault.as_deref_mut(),
+        )
+    }
+
+    pub fn play_batch_sol(ctx: Context<PlayBatchSolCtx>, ix: PlayBatchIx) -> Result<()> {
+        let box_config =
+            load_box_config(&ctx.accounts.box_config, Currency::Sol, Pubkey::default())?;
+        if ix.count == 0 || ix.count as usize > MAX_BATCH_SIZE {
+            return err!(ErrorCode::InvalidBatchSize);
+        }
+        let count = ix.count as u64;
+        let price = box_config
+            .price
+            .checked_mul(count)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let cpi_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.game.to_account_info(),
+            },
+        );
+
+        system_program::transfer(cpi_context, price)?;
+
+        let prizes = box_config.prize_table();
+        let reserve = prizes
+            .max_prize()
+            .checked_mul(count)
+            .ok_or(ErrorCode::MathOverflow)?
+            .max(price);
+        if reserve > sol_surplus(&ctx.accounts.game)? {
+            return err!(ErrorCode::InsufficientBankroll);
+        }
+        add_liability(&mut ctx.accounts.game.sol_liabilities, reserve)?;
+
+        let clock = Clock::get()?;
+        let play_batch = &mut ctx.accounts.play_batch;
+        play_batch.bump = ctx.bumps.play_batch;
+        play_batch.authority = ctx.accounts.payer.key();
+        play_batch.game = ctx.accounts.game.key();
+        play_batch.box_config = ctx.accounts.box_config.key();
+        play_batch.count = ix.count;
+        play_batch.prize_indices = Vec::new();
+        play_batch.win_amount = 0;
+        play_batch.wager = price;
+        play_batch.reserved = reserve;
+        play_batch.prizes = prizes;
+        play_batch.currency = Currency::Sol;
+        play_batch.mint = Pubkey::default();
+        play_batch.status = PlayerStatus::Pending;
+        play_batch.randomness_source = ctx.accounts.game.randomness_source;
+        play_batch.commitment = ix.commitment;
+        play_batch.commit_slot = clock.slot;
+        play_batch.created_at = clock.unix_timestamp;
+
+        emit!(PlayStarted {
+            game: play_batch.game,
+            player: play_batch.authority,
+            play: play_batch.key(),
+            box_id: box_config.box_id,
+            currency: play_batch.currency,
+            mint: play_batch.mint,
+            count: play_batch.count,
+            wager: play_batch.wager,
+            reserved: play_batch.reserved,
+        });
+
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(
+            ctx.bumps.player_profile,
+            ctx.accounts.game.key(),
+            ctx.accounts.payer.key(),
+        );
+        player_profile.record_play(
+            Currency::Sol,
+            Pubkey::default(),
+            box_config.box_id,
+            count,
+            price,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn play_batch_token(ctx: Context<PlayBatchTokenCtx>, ix: PlayBatchIx) -> Result<()> {
+        let box_config = load_box_config(
+            &ctx.accounts.box_config,
+            Currency::Token,
+            ctx.accounts.mint.key(),
+        )?;
+        if ix.count == 0 || ix.count as usize > MAX_BATCH_SIZE {
+            return err!(ErrorCode::InvalidBatchSize);
+        }
+        let count = ix.count as u64;
+        let price = box_config
+            .price
+            .checked_mul(count)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let wager = transfer_in(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payer_token_account,
+            &mut ctx.accounts.game_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.payer,
+            price,
+        )?;
+        let prizes = box_config.prize_table();
+        let reserve = prizes
+            .max_prize()
+            .checked_mul(count)
+            .ok_or(ErrorCode::MathOverflow)?
+            .max(wager);
+        let surplus = ctx
+            .accounts
+            .game_token_account
+            .amount
+            .saturating_sub(ctx.accounts.token_vault.liabilities);
+        if reserve > surplus {
+            return err!(ErrorCode::InsufficientBankroll);
+        }
+        add_liability(&mut ctx.accounts.token_vault.liabilities, reserve)?;
+
+        let clock = Clock::get()?;
+        let play_batch = &mut ctx.accounts.play_batch;
+        play_batch.bump = ctx.bumps.play_batch;
+        play_batch.authority = ctx.accounts.payer.key();
+        play_batch.game = ctx.accounts.game.key();
+        play_batch.box_config = ctx.accounts.box_config.key();
+        play_batch.count = ix.count;
+        play_batch.prize_indices = Vec::new();
+        play_batch.win_amount = 0;
+        play_batch.wager = wager;
+        play_batch.reserved = reserve;
+        play_batch.prizes = prizes;
+        play_batch.currency = Currency::Token;
+        play_batch.mint = ctx.accounts.mint.key();
+        play_batch.status = PlayerStatus::Pending;
+        play_batch.randomness_source = ctx.accounts.game.randomness_source;
+        play_batch.commitment = ix.commitment;
+        play_batch.commit_slot = clock.slot;
+        play_batch.created_at = clock.unix_timestamp;
+
+        emit!(PlayStarted {
+            game: play_batch.game,
+            player: play_batch.authority,
+            play: play_batch.key(),
+            box_id: box_config.box_id,
+            currency: play_batch.currency,
+            mint: play_batch.mint,
+            count: play_batch.count,
+            wager: play_batch.wager,
+            reserved: play_batch.reserved,
+        });
+
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(
+            ctx.bumps.player_profile,
+            ctx.accounts.game.key(),
+            ctx.accounts.payer.key(),
+        );
+        player_profile.record_play(
+            Currency::Token,
+            ctx.accounts.mint.key(),
+            box_config.box_id,
+            count,
+            wager,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn reveal_batch(ctx: Context<RevealBatchCtx>, ix: RevealIx) -> Result<()> {
+        let play_batch = &mut ctx.accounts.play_batch;
+
+        if hash(&ix.seed).to_bytes() != play_batch.commitment {
+            return err!(ErrorCode::InvalidReveal);
+        }
+
+        let slot_hash = find_slot_hash(&ctx.accounts.slot_hashes, play_batch.commit_slot)?;
+        let randomness = hashv(&[&ix.seed, &slot_hash, play_batch.key().as_ref()]).to_bytes();
+        play_batch.settle(
+            &mut ctx.accounts.game,
+            ctx.accounts.token_vault.as_deref_mut(),
+            &ctx.accounts.box_config,
+            &randomness,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn fulfill_batch_randomness(
+        ctx: Context<FulfillBatchRandomnessCtx>,
+        ix: FulfillRandomnessIx,
+    ) -> Result<()> {
+        let play_batch = &mut ctx.accounts.play_batch;
+        let randomness = hashv(&[&ix.randomness, play_batch.key().as_ref()]).to_bytes();
+        play_batch.settle(
+            &mut ctx.accounts.game,
+            ctx.accounts.token_vault.as_deref_mut(),
+            &ctx.accounts.box_config,
+            &randomness,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn claim_batch_sol(ctx: Context<ClaimBatchSolCtx>) -> Result<()> {
+        let play_batch = &mut ctx.accounts.play_batch;
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(
+            ctx.bumps.player_profile,
+            play_batch.game,
+            play_batch.authority,
+        );
+
+        let amount = play_batch.stake().claim(
+            &mut ctx.accounts.game.sol_liabilities,
+            Some(&mut **player_profile),
+        )?;
+        ctx.accounts.game.sub_lamports(amount)?;
+        ctx.accounts.payer.add_lamports(amount)?;
+
+        Ok(())
+    }
+
+    pub fn claim_batch_token(ctx: Context<ClaimBatchTokenCtx>) -> Result<()> {
+        let play_batch = &mut ctx.accounts.play_batch;
+        let player_profile = &mut ctx.accounts.player_profile;
+        player_profile.init_if_new(
+            ctx.bumps.player_profile,
+            play_batch.game,
+            play_batch.authority,
+        );
+
+        let amount = play_batch.stake().claim(
+            &mut ctx.accounts.token_vault.liabilities,
+            Some(&mut **player_profile),
+        )?;
+        transfer_out(
+            &ctx.accounts.game,
+            &ctx.accounts.token_program,
+            &ctx.accounts.game_token_account,
+            &ctx.accounts.payer_token_account,
+            &ctx.accounts.mint,
+            amount,
+        )
+    }
+
+    pub fn expire_batch(ctx: Context<ExpireBatchCtx>) -> Result<()> {
+        ctx.accounts.play_batch.stake().expire(
+            &mut ctx.accounts.game,
+            ctx.accounts.token_vault.as_deref_mut(),
+        )
+    }
+
+    pub fn queue_withdrawal(ctx: Context<QueueWithdrawalCtx>, ix: QueueWithdrawalIx) -> Result<()> {
+        if (ix.currency == Currency::Sol) != (ix.mint == Pubkey::default()) {
+            return err!(ErrorCode::InvalidMint);
+        }
+        let now = Clock::get()?.unix_timestamp;
+        let unlock_at = now
+            .checked_add(ctx.accounts.game.withdrawal_delay)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        let pending_withdrawal = &mut ctx.accounts.pending_withdrawal;
+        pending_withdrawal.bump = ctx.bumps.pending_withdrawal;
+        pending_withdrawal.game = ctx.accounts.game.key();
+        pending_withdrawal.withdrawal_id = ix.withdrawal_id;
+        pending_withdrawal.currency = ix.currency;
+        pending_withdrawal.mint = ix.mint;
+        pending_withdrawal.amount = ix.amount;
+        pending_withdrawal.destination = ix.destination;
+        pending_withdrawal.unlock_at = unlock_at;
+        pending_withdrawal.created_at = now;
+
+        emit!(WithdrawalQueued {
+            game: pending_withdrawal.game,
+            withdrawal_id: ix.withdrawal_id,
+            currency: ix.currency,
+            mint: ix.mint,
+            amount: ix.amount,
+            destination: ix.destination,
+            unlock_at,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_withdrawal(ctx: Context<CancelWithdrawalCtx>) -> Result<()> {
+        emit!(WithdrawalCancelled {
+            game: ctx.accounts.game.key(),
+            withdrawal_id: ctx.accounts.pending_withdrawal.withdrawal_id,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_withdrawal_sol(ctx: Context<WithdrawSolsCtx>) -> Result<()> {
+        let pending_withdrawal = &ctx.accounts.pending_withdrawal;
+        check_unlocked(pending_withdrawal.unlock_at)?;
+        let surplus = sol_surplus(&ctx.accounts.game)?;
+        let amount = pending_withdrawal.amount.unwrap_or(surplus);
+        if amount > surplus {
+            return err!(ErrorCode::InsufficientSurplus);
+        }
+
+        ctx.accounts.game.sub_lamports(amount)?;
+        ctx.accounts.destination.add_lamports(amount)?;
+
+        emit!(Withdrawn {
+            game: ctx.accounts.game.key(),
+            withdrawal_id: pending_withdrawal.withdrawal_id,
+            currency: Currency::Sol,
+            mint: Pubkey::default(),
+            amount,
+            destination: pending_withdrawal.destination,
+        });
+
+        Ok(())
+    }
+
+    pub fn execute_withdrawal_token(ctx: Context<TokenWithdrawCtx>) -> Result<()> {
+        let pending_withdrawal = &ctx.accounts.pending_withdrawal;
+        check_unlocked(pending_withdrawal.unlock_at)?;
+        let surplus = ctx
+            .accounts
+            .game_token_account
+            .amount
+            .saturating_sub(ctx.accounts.token_vault.liabilities);
+        let amount = pending_withdrawal.amount.unwrap_or(surplus);
+        if amount > surplus {
+            return err!(ErrorCode::InsufficientSurplus);
+        }
+        transfer_out(
+            &ctx.accounts.game,
+            &ctx.accounts.token_program,
+            &ctx.accounts.game_token_account,
+            &ctx.accounts.destination_token_account,
+            &ctx.accounts.mint,
+            amount,
+        )?;
+
+        emit!(Withdrawn {
+            game: ctx.accounts.game.key(),
+            withdrawal_id: ctx.accounts.pending_withdrawal.withdrawal_id,
+            currency: Currency::Token,
+            mint: ctx.accounts.mint.key(),
+            amount,
+            destination: ctx.accounts.pending_withdrawal.destination,
+        });
+
+        Ok(())
+    }
+}
+
+// `rand_num` must be drawn from [0, sum of chances), which `validate_prize_table`
+// guarantees for every stored box, so index i wins with probability chances[i] / total.
+fn pick_index(chances: &[u64], rand_num: u64) -> usize {
+    let mut cumulative_chance: u64 = 0;
+    for (i, &chance) in chances.iter().enumerate() {
+        cumulative_chance += chance;
+        if rand_num < cumulative_chance {
+            return i;
+        }
+    }
+    chances.len() - 1
+}
+
+fn draw(prizes: &PrizeTable, randomness: &[u8; 32]) -> (u64, usize) {
+    // 128 bits keeps the modulo bias negligible for any u64 denominator.
+    let rand_num = (u128::from_le_bytes(randomness[..16].try_into().unwrap())
+        % prizes.denominator as u128) as u64;
+
+    (rand_num, pick_index(&prizes.chances, rand_num))
+}
+
+// Auto-claim plays are paid inside the oracle callback, so they need an
+// oracle game and claims that are not paused.
+fn check_auto_claim(game: &Game) -> Result<()> {
+    if game.randomness_source != RandomnessSource::Oracle {
+        return err!(ErrorCode::AutoClaimRequiresOracle);
+    }
+    if game.paused & PAUSE_CLAIMS != 0 {
+        return err!(ErrorCode::GamePaused);
+    }
+
+    Ok(())
+}
+
+// What a play that was never settled pays back once its slot hash has left
+// SlotHashes. A commit-reveal player could have revealed in time, so the wager
+// is forfeited; an oracle that never answered refunds it.
+fn expiry_refund(source: RandomnessSource, commit_slot: u64, wager: u64) -> Result<u64> {
+    if Clock::get()?.slot <= commit_slot.saturating_add(PLAY_EXPIRY_SLOTS) {
+        return err!(ErrorCode::PlayNotExpired);
+    }
+
+    Ok(match source {
+        RandomnessSource::CommitReveal => 0,
+        RandomnessSource::Oracle => wager,
+    })
+}
+
+// Queued withdrawals and treasury changes run between unlock_at and the end of
+// TIMELOCK_WINDOW; anything older has to be queued again.
+fn check_unlocked(unlock_at: i64) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    if now < unlock_at {
+        return err!(ErrorCode::WithdrawalLocked);
+    }
+    if now > unlock_at.saturating_add(TIMELOCK_WINDOW) {
+        return err!(ErrorCode::TimelockExpired);
+    }
+
+    Ok(())
+}
+
+// Lamports the game can spend without touching player liabilities or
+// dropping below rent exemption.
+fn sol_surplus(game: &Account<Game>) -> Result<u64> {
+    let game_info = game.to_account_info();
+    let rent_exempt = Rent::get()?.minimum_balance(game_info.data_len());
+
+    Ok(game_info
+        .lamports()
+        .saturating_sub(rent_exempt)
+        .saturating_sub(game.sol_liabilities))
+}
+
+// Moves tokens from a payer into the game. Transfer-fee mints withhold part of
+// what is sent, so this returns the amount that actually arrived.
+fn transfer_in<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &mut InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<u64> {
+    let cpi_accounts = TransferChecked {
+        from: from.to_account_info(),
+        to: to.to_account_info(),
+        authority: authority.to_account_info(),
+        mint: mint.to_account_info(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+
+    let balance_before = to.amount;
+    transfer_checked(cpi_ctx, amount, mint.decimals)?;
+
+    to.reload()?;
+    let received = to
+        .amount
+        .checked_sub(balance_before)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(received)
+}
+
+// Pays tokens out of the game's token account, signed by the game PDA.
+fn transfer_out<'info>(
+    game: &Account<'info, Game>,
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    amount: u64,
+) -> Result<()> {
+    let game_id = game_id_seed(game.game_id);
+
+    let game_seeds = &[
+        GAME_PREFIX.as_bytes(),
+        game.creator.as_ref(),
+        &game_id,
+        &[game.bump],
+    ];
+
+    let game_signer_seeds = &[&game_seeds[..]];
+
+    let accounts = TransferChecked {
+        from: from.to_account_info(),
+        to: to.to_account_info(),
+        authority: game.to_account_info(),
+        mint: mint.to_account_info(),
+    };
+
+    let tx_ctx =
+        CpiContext::new_with_signer(token_program.to_account_info(), accounts, game_signer_seeds);
+
+    transfer_checked(tx_ctx, amount, mint.decimals)
+}
+
+// Game id 0 keeps the address games had before ids existed, since an empty
+// seed does not change the derived address.
+fn game_id_seed(game_id: u64) -> Vec<u8> {
+    if game_id == 0 {
+        Vec::new()
+    } else {
+        game_id.to_le_bytes().to_vec()
+    }
+}
+
+fn add_liability(liabilities: &mut u64, amount: u64) -> Result<()> {
+    *liabilities = liabilities
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+fn sub_liability(liabilities: &mut u64, amount: u64) -> Result<()> {
+    *liabilities = liabilities
+        .checked_sub(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    Ok(())
+}
+
+fn validate_prize_table(denominator: u64, chances: &[u64], win_values: &[u64]) -> Result<()> {
+    if chances.is_empty() {
+        return err!(ErrorCode::EmptyPrizeTable);
+    }
+    if chances.len() > MAX_PRIZES {
+        return err!(ErrorCode::TooManyPrizes);
+    }
+    if chances.len() != win_values.len() {
+        return err!(ErrorCode::PrizeTableLengthMismatch);
+    }
+
+    let mut total_chance: u64 = 0;
+    for &chance in chances {
+        total_chance = total_chance
+            .checked_add(chance)
+            .ok_or(ErrorCode::InvalidOddsTotal)?;
+    }
+    if denominator == 0 || total_chance != denominator {
+        return err!(ErrorCode::InvalidOddsTotal);
+    }
+
+    Ok(())
+}
+
+// Play accounts derive the box from its id, so an id that was never created
+// shows up here as an empty account rather than failing in account checks.
+fn load_box_config(info: &AccountInfo, currency: Currency, mint: Pubkey) -> Result<BoxConfig> {
+    if info.owner != &crate::ID || info.data_is_empty() {
+        return err!(ErrorCode::InvalidBoxType);
+    }
+    let box_config = BoxConfig::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+    if box_config.retired {
+        return err!(ErrorCode::BoxRetired);
+    }
+    if box_config.currency != currency {
+        return err!(ErrorCode::WrongCurrency);
+    }
+    if box_config.mint != mint {
+        return err!(ErrorCode::InvalidMint);
+    }
+
+    Ok(box_config)
+}
+
+// Expected payout per play, sum(chance * win) / denominator, must stay within
+// max_rtp_bps of the price. Compared cross-multiplied to keep it exact.
+fn check_operator_rtp(
+    max_rtp_bps: u16,
+    price: u64,
+    denominator: u64,
+    chances: &[u64],
+    win_values: &[u64],
+) -> Result<()> {
+    let mut expected = 0u128;
+    for (chance, win) in chances.iter().zip(win_values) {
+        expected = expected
+            .checked_add(*chance as u128 * *win as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+    }
+    let payout = expected
+        .checked_mul(BPS_DENOMINATOR as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let limit = (price as u128 * denominator as u128)
+        .checked_mul(max_rtp_bps as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if payout > limit {
+        return err!(ErrorCode::BoxExceedsOperatorLimit);
+    }
+
+    Ok(())
+}
+
+// SlotHashes is too large to deserialize on-chain, so walk the raw
+// (slot, hash) entries instead. Entries are ordered newest first.
+fn find_slot_hash(slot_hashes: &AccountInfo, slot: u64) -> Result<[u8; 32]> {
+    let data = slot_hashes.try_borrow_data()?;
+    let len = u64::from_le_bytes(data[..8].try_into().unwrap()) as usize;
+
+    for i in 0..len {
+        let offset = 8 + i * 40;
+        let entry_slot = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        if entry_slot == slot {
+            return Ok(data[offset + 8..offset + 40].try_into().unwrap());
+        }
+        if entry_slot < slot {
+            break;
+        }
+    }
+
+    err!(ErrorCode::SlotHashNotFound)
+}
+
+#[derive(Accounts)]
+#[instruction(ix: InitGameIx)]
+pub struct InitGameCtx<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = GAME_SPACE,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            payer.key().as_ref(),
+            &game_id_seed(ix.game_id)
+        ],
+        bump
+    )]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateGameCtx<'info> {
+    /// CHECK: legacy layout is decoded by hand in `migrate_game`
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [GAME_PREFIX.as_bytes(), payer.key().as_ref()],
+        bump
+    )]
+    game: UncheckedAccount<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigratePlayerCtx<'info> {
+    /// CHECK: legacy layout is decoded by hand in `migrate_player`
+    #[account(mut, owner = crate::ID)]
+    player: UncheckedAccount<'info>,
+    // Legacy records name no game; they belong to a creator's original game.
+    #[account(mut, constraint = game.game_id == 0 @ ErrorCode::InvalidGame)]
+    game: Account<'info, Game>,
+    #[account(mut, has_one = game)]
+    token_vault: Option<Account<'info, TokenVault>>,
+    #[account(constraint = game_token_account.owner == game.key() @ ErrorCode::InvalidGame)]
+    game_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthorityCtx<'info> {
+    #[account(mut, constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin)]
+    game: Account<'info, Game>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthorityCtx<'info> {
+    #[account(
+        mut,
+        constraint = game.pending_authority == payer.key() @ ErrorCode::InvalidPendingAuthority
+    )]
+    game: Account<'info, Game>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ManageRolesCtx<'info> {
+    #[account(mut, constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin)]
+    game: Account<'info, Game>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct QueueTreasuryChangeCtx<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 100,
+        seeds = [TREASURY_CHANGE_PREFIX.as_bytes(), game.key().as_ref()],
+        bump
+    )]
+    treasury_change: Account<'info, PendingTreasuryChange>,
+    #[account(constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin)]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelTreasuryChangeCtx<'info> {
+    #[account(mut, close = payer, has_one = game)]
+    treasury_change: Account<'info, PendingTreasuryChange>,
+    #[account(constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin)]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyTreasuryChangeCtx<'info> {
+    #[account(mut, close = payer, has_one = game)]
+    treasury_change: Account<'info, PendingTreasuryChange>,
+    #[account(mut, constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin)]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPauseCtx<'info> {
+    #[account(mut, constraint = game.is_operator(&payer.key()) @ ErrorCode::InvalidOperator)]
+    game: Account<'info, Game>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetOpenDepositsCtx<'info> {
+    #[account(mut, constraint = game.is_operator(&payer.key()) @ ErrorCode::InvalidOperator)]
+    game: Account<'info, Game>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AddTokenCtx<'info> {
+    #[account(mut, constraint = game.is_operator(&payer.key()) @ ErrorCode::InvalidOperator)]
+    game: Account<'info, Game>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 100,
+        seeds = [VAULT_PREFIX.as_bytes(), game.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    token_vault: Account<'info, TokenVault>,
+    #[account(
+        init_if_needed,
+        payer = payer, 
+        associated_token::mint = mint, 
+        associated_token::authority = game,
+        associated_token::token_program = token_program
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    payer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+    associated_token_program: Program<'info, AssociatedToken>,
+    rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct DisableTokenCtx<'info> {
+    #[account(constraint = game.is_operator(&payer.key()) @ ErrorCode::InvalidOperator)]
+    game: Account<'info, Game>,
+    #[account(mut, has_one = game)]
+    token_vault: Account<'info, TokenVault>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveTokenCtx<'info> {
+    #[account(
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = game,
+        has_one = mint,
+        constraint = token_vault.liabilities == 0 @ ErrorCode::OutstandingLiabilities
+    )]
+    token_vault: Account<'info, TokenVault>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = game,
+        associated_token::token_program = token_program,
+        constraint = game_token_account.amount == 0 @ ErrorCode::VaultNotEmpty
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    mint: InterfaceAccount<'info, Mint>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: CreateBoxIx)]
+pub struct CreateBoxCtx<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 270,
+        seeds = [BOX_PREFIX.as_bytes(), game.key().as_ref(), &[ix.box_id]],
+        bump
+    )]
+    box_config: Account<'info, BoxConfig>,
+    // Operators are held to the owner's RTP limit in the handler.
+    #[account(constraint = game.is_operator(&payer.key()) @ ErrorCode::InvalidOperator)]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateBoxCtx<'info> {
+    #[account(mut, has_one = game, constraint = !box_config.retired @ ErrorCode::BoxRetired)]
+    box_config: Account<'info, BoxConfig>,
+    #[account(constraint = game.is_operator(&payer.key()) @ ErrorCode::InvalidOperator)]
+    game: Account<'info, Game>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: PlayIx)]
+pub struct PlaySolCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(init,
+        payer=payer,
+        space=PLAYER_SPACE,
+        seeds = [
+            PALYER_PREFIX.as_bytes(),
+            game.key().as_ref(),
+            payer.key().as_ref(),
+            &player_profile.nonce.to_le_bytes()
+        ],
+        bump
+        )]
+    player: Account<'info, Player>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_PLAYS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    /// CHECK: derived from the box id and read in `load_box_config`
+    #[account(seeds = [BOX_PREFIX.as_bytes(), game.key().as_ref(), &[ix.box_id]], bump)]
+    box_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: PlayIx)]
+pub struct PlayTokenCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(init,
+        payer=payer,
+        space=PLAYER_SPACE,
+        seeds = [
+            PALYER_PREFIX.as_bytes(),
+            game.key().as_ref(),
+            payer.key().as_ref(),
+            &player_profile.nonce.to_le_bytes()
+        ],
+        bump)]
+    player: Account<'info, Player>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_PLAYS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    /// CHECK: derived from the box id and read in `load_box_config`
+    #[account(seeds = [BOX_PREFIX.as_bytes(), game.key().as_ref(), &[ix.box_id]], bump)]
+    box_config: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [VAULT_PREFIX.as_bytes(), game.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump,
+        constraint = token_vault.enabled @ ErrorCode::TokenDisabled
+    )]
+    token_vault: Account<'info, TokenVault>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = game,
+        associated_token::token_program = token_program
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    payer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSolCtx<'info> {
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.open_deposits || game.is_operator(&payer.key()) @ ErrorCode::DepositsClosed
+    )]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct DepositTokenCtx<'info> {
+    #[account(
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.open_deposits || game.is_operator(&payer.key()) @ ErrorCode::DepositsClosed
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        mut,
+        seeds = [VAULT_PREFIX.as_bytes(), game.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump,
+        constraint = token_vault.enabled @ ErrorCode::TokenDisabled
+    )]
+    token_vault: Account<'info, TokenVault>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = game,
+        associated_token::token_program = token_program
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    payer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetInstantPrizeLimitCtx<'info> {
+    #[account(mut, constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin)]
+    game: Account<'info, Game>,
+    #[account(mut, has_one = game)]
+    token_vault: Option<Account<'info, TokenVault>>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRandomnessSourceCtx<'info> {
+    #[account(mut, constraint = game.authority == payer.key() @ ErrorCode::InvalidAdmin)]
+    game: Account<'info, Game>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RevealCtx<'info> {
+    #[account(
+        mut,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = player.status == PlayerStatus::Pending @ ErrorCode::AlreadyRevealed,
+        constraint = player.randomness_source == RandomnessSource::CommitReveal @ ErrorCode::WrongRandomnessSource
+    )]
+    player: Account<'info, Player>,
+    #[account(mut)]
+    game: Account<'info, Game>,
+    #[account(constraint = box_config.key() == player.box_config @ ErrorCode::InvalidBox)]
+    box_config: Account<'info, BoxConfig>,
+    #[account(
+        mut,
+        has_one = game,
+        constraint = token_vault.mint == player.mint @ ErrorCode::InvalidMint
+    )]
+    token_vault: Option<Account<'info, TokenVault>>,
+    /// CHECK: address is pinned to the SlotHashes sysvar and read raw in `find_slot_hash`
+    #[account(address = slot_hashes::ID)]
+    slot_hashes: UncheckedAccount<'info>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FulfillRandomnessCtx<'info> {
+    #[account(
+        mut,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = player.status == PlayerStatus::Pending @ ErrorCode::AlreadyRevealed,
+        constraint = player.randomness_source == RandomnessSource::Oracle @ ErrorCode::WrongRandomnessSource
+    )]
+    player: Account<'info, Player>,
+    #[account(mut, constraint = game.oracle == oracle.key() @ ErrorCode::InvalidOracle)]
+    game: Account<'info, Game>,
+    #[account(constraint = box_config.key() == player.box_config @ ErrorCode::InvalidBox)]
+    box_config: Account<'info, BoxConfig>,
+    #[account(
+        mut,
+        has_one = game,
+        constraint = token_vault.mint == player.mint @ ErrorCode::InvalidMint
+    )]
+    token_vault: Option<Account<'info, TokenVault>>,
+    oracle: Signer<'info>,
+    // The rest is only needed to pay auto-claim plays from the callback.
+    /// CHECK: only receives lamports, pinned to the player's wallet
+    #[account(mut, address = player.authority @ ErrorCode::InvalidPlayer)]
+    authority: Option<UncheckedAccount<'info>>,
+    #[account(
+        mut,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), player.authority.as_ref()],
+        bump = player_profile.bump
+    )]
+    player_profile: Option<Account<'info, PlayerProfile>>,
+    #[account(
+        mut,
+        constraint = game_token_account.owner == game.key() @ ErrorCode::InvalidGame,
+        constraint = game_token_account.mint == player.mint @ ErrorCode::InvalidMint
+    )]
+    game_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = authority_token_account.owner == player.authority @ ErrorCode::InvalidPlayer,
+        constraint = authority_token_account.mint == player.mint @ ErrorCode::InvalidMint
+    )]
+    authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(address = player.mint @ ErrorCode::InvalidMint)]
+    mint: Option<InterfaceAccount<'info, Mint>>,
+    token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewardSolCtx<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = player.authority == payer.key() @ ErrorCode::InvalidPlayer,
+        constraint = player.status != PlayerStatus::Claimed @ ErrorCode::AlreadyClaimed,
+        constraint = player.status.is_settled() @ ErrorCode::NotRevealed,
+        constraint = player.currency == Currency::Sol @ ErrorCode::WrongCurrency
+    )]
+    player: Account<'info, Player>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_CLAIMS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewardTokenCtx<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = player.authority == payer.key() @ ErrorCode::InvalidPlayer,
+        constraint = player.status != PlayerStatus::Claimed @ ErrorCode::AlreadyClaimed,
+        constraint = player.status.is_settled() @ ErrorCode::NotRevealed,
+        constraint = player.currency == Currency::Token @ ErrorCode::WrongCurrency,
+        constraint = player.mint == mint.key() @ ErrorCode::InvalidMint
+    )]
+    player: Account<'info, Player>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_CLAIMS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = game,
+        associated_token::token_program = token_program
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [VAULT_PREFIX.as_bytes(), game.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump
+    )]
+    token_vault: Account<'info, TokenVault>,
+    mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    payer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClosePlayerCtx<'info> {
+    #[account(
+        mut,
+        close = payer,
+        constraint = player.authority == payer.key() @ ErrorCode::InvalidPlayer,
+        constraint = player.status != PlayerStatus::Pending @ ErrorCode::NotRevealed,
+        constraint = player.win_amount == 0 @ ErrorCode::UnclaimedReward
+    )]
+    player: Account<'info, Player>,
+    #[account(mut)]
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpirePlayCtx<'info> {
+    #[account(
+        mut,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = player.status == PlayerStatus::Pending @ ErrorCode::AlreadyRevealed
+    )]
+    player: Account<'info, Player>,
+    #[account(mut)]
+    game: Account<'info, Game>,
+    #[account(
+        mut,
+        has_one = game,
+        constraint = token_vault.mint == player.mint @ ErrorCode::InvalidMint
+    )]
+    token_vault: Option<Account<'info, TokenVault>>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: PlayBatchIx)]
+pub struct PlayBatchSolCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(
+        init,
+        payer = payer,
+        space = BATCH_SPACE,
+        seeds = [
+            BATCH_PREFIX.as_bytes(),
+            game.key().as_ref(),
+            payer.key().as_ref(),
+            &player_profile.nonce.to_le_bytes()
+        ],
+        bump
+    )]
+    play_batch: Account<'info, PlayBatch>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_PLAYS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    /// CHECK: derived from the box id and read in `load_box_config`
+    #[account(seeds = [BOX_PREFIX.as_bytes(), game.key().as_ref(), &[ix.box_id]], bump)]
+    box_config: UncheckedAccount<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: PlayBatchIx)]
+pub struct PlayBatchTokenCtx<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(
+        init,
+        payer = payer,
+        space = BATCH_SPACE,
+        seeds = [
+            BATCH_PREFIX.as_bytes(),
+            game.key().as_ref(),
+            payer.key().as_ref(),
+            &player_profile.nonce.to_le_bytes()
+        ],
+        bump
+    )]
+    play_batch: Account<'info, PlayBatch>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_PLAYS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    /// CHECK: derived from the box id and read in `load_box_config`
+    #[account(seeds = [BOX_PREFIX.as_bytes(), game.key().as_ref(), &[ix.box_id]], bump)]
+    box_config: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [VAULT_PREFIX.as_bytes(), game.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump,
+        constraint = token_vault.enabled @ ErrorCode::TokenDisabled
+    )]
+    token_vault: Account<'info, TokenVault>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = game,
+        associated_token::token_program = token_program
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    payer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealBatchCtx<'info> {
+    #[account(
+        mut,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = play_batch.status == PlayerStatus::Pending @ ErrorCode::AlreadyRevealed,
+        constraint = play_batch.randomness_source == RandomnessSource::CommitReveal @ ErrorCode::WrongRandomnessSource
+    )]
+    play_batch: Account<'info, PlayBatch>,
+    #[account(mut)]
+    game: Account<'info, Game>,
+    #[account(constraint = box_config.key() == play_batch.box_config @ ErrorCode::InvalidBox)]
+    box_config: Account<'info, BoxConfig>,
+    #[account(
+        mut,
+        has_one = game,
+        constraint = token_vault.mint == play_batch.mint @ ErrorCode::InvalidMint
+    )]
+    token_vault: Option<Account<'info, TokenVault>>,
+    /// CHECK: address is pinned to the SlotHashes sysvar and read raw in `find_slot_hash`
+    #[account(address = slot_hashes::ID)]
+    slot_hashes: UncheckedAccount<'info>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FulfillBatchRandomnessCtx<'info> {
+    #[account(
+        mut,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = play_batch.status == PlayerStatus::Pending @ ErrorCode::AlreadyRevealed,
+        constraint = play_batch.randomness_source == RandomnessSource::Oracle @ ErrorCode::WrongRandomnessSource
+    )]
+    play_batch: Account<'info, PlayBatch>,
+    #[account(mut, constraint = game.oracle == oracle.key() @ ErrorCode::InvalidOracle)]
+    game: Account<'info, Game>,
+    #[account(constraint = box_config.key() == play_batch.box_config @ ErrorCode::InvalidBox)]
+    box_config: Account<'info, BoxConfig>,
+    #[account(
+        mut,
+        has_one = game,
+        constraint = token_vault.mint == play_batch.mint @ ErrorCode::InvalidMint
+    )]
+    token_vault: Option<Account<'info, TokenVault>>,
+    oracle: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBatchSolCtx<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = play_batch.authority == payer.key() @ ErrorCode::InvalidPlayer,
+        constraint = play_batch.status.is_settled() @ ErrorCode::NotRevealed,
+        constraint = play_batch.currency == Currency::Sol @ ErrorCode::WrongCurrency
+    )]
+    play_batch: Account<'info, PlayBatch>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_CLAIMS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimBatchTokenCtx<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = play_batch.authority == payer.key() @ ErrorCode::InvalidPlayer,
+        constraint = play_batch.status.is_settled() @ ErrorCode::NotRevealed,
+        constraint = play_batch.currency == Currency::Token @ ErrorCode::WrongCurrency,
+        constraint = play_batch.mint == mint.key() @ ErrorCode::InvalidMint
+    )]
+    play_batch: Account<'info, PlayBatch>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_CLAIMS == 0 @ ErrorCode::GamePaused
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PROFILE_SPACE,
+        seeds = [PROFILE_PREFIX.as_bytes(), game.key().as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    player_profile: Account<'info, PlayerProfile>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = game,
+        associated_token::token_program = token_program
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [VAULT_PREFIX.as_bytes(), game.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump
+    )]
+    token_vault: Account<'info, TokenVault>,
+    mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint)]
+    payer_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireBatchCtx<'info> {
+    #[account(
+        mut,
+        has_one = game @ ErrorCode::InvalidGame,
+        constraint = play_batch.status == PlayerStatus::Pending @ ErrorCode::AlreadyRevealed
+    )]
+    play_batch: Account<'info, PlayBatch>,
+    #[account(mut)]
+    game: Account<'info, Game>,
+    #[account(
+        mut,
+        has_one = game,
+        constraint = token_vault.mint == play_batch.mint @ ErrorCode::InvalidMint
+    )]
+    token_vault: Option<Account<'info, TokenVault>>,
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(ix: QueueWithdrawalIx)]
+pub struct QueueWithdrawalCtx<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 150,
+        seeds = [
+            WITHDRAWAL_PREFIX.as_bytes(),
+            game.key().as_ref(),
+            &ix.withdrawal_id.to_le_bytes()
+        ],
+        bump
+    )]
+    pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(constraint = game.treasurer == payer.key() @ ErrorCode::InvalidTreasurer)]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWithdrawalCtx<'info> {
+    #[account(mut, close = payer, has_one = game)]
+    pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(
+        constraint = game.treasurer == payer.key() || game.authority == payer.key()
+            @ ErrorCode::InvalidTreasurer
+    )]
+    game: Account<'info, Game>,
+    #[account(mut)]
+    payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSolsCtx<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = game,
+        constraint = pending_withdrawal.currency == Currency::Sol @ ErrorCode::WrongCurrency
+    )]
+    pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_WITHDRAWALS == 0 @ ErrorCode::GamePaused,
+        constraint = game.treasurer == payer.key() @ ErrorCode::InvalidTreasurer
+    )]
+    game: Account<'info, Game>,
+    /// CHECK: only receives lamports, pinned to the queued destination
+    #[account(
+        mut,
+        address = pending_withdrawal.destination @ ErrorCode::InvalidDestination
+    )]
+    destination: UncheckedAccount<'info>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TokenWithdrawCtx<'info> {
+    #[account(
+        mut,
+        close = payer,
+        has_one = game,
+        has_one = mint,
+        constraint = pending_withdrawal.currency == Currency::Token @ ErrorCode::WrongCurrency
+    )]
+    pending_withdrawal: Account<'info, PendingWithdrawal>,
+    #[account(
+        mut,
+        seeds = [
+            GAME_PREFIX.as_bytes(),
+            game.creator.as_ref(),
+            &game_id_seed(game.game_id)
+        ],
+        bump = game.bump,
+        constraint = game.paused & PAUSE_WITHDRAWALS == 0 @ ErrorCode::GamePaused,
+        constraint = game.treasurer == payer.key() @ ErrorCode::InvalidTreasurer
+    )]
+    game: Account<'info, Game>,
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = game,
+        associated_token::token_program = token_program
+    )]
+    game_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [VAULT_PREFIX.as_bytes(), game.key().as_ref(), mint.key().as_ref()],
+        bump = token_vault.bump
+    )]
+    token_vault: Account<'info, TokenVault>,
+    mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        token::mint = mint,
+        address = pending_withdrawal.destination @ ErrorCode::InvalidDestination
+    )]
+    destination_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    payer: Signer<'info>,
+    token_program: Interface<'info, TokenInterface>,
+    system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct Game {
+    pub bump: u8,
+    pub authority: Pubkey,
+    pub creator: Pubkey, // game PDA seed, never changes
+    pub pending_authority: Pubkey,
+    pub randomness_source: RandomnessSource,
+    pub oracle: Pubkey,
+    pub sol_liabilities: u64,
+    pub paused: u8, // PAUSE_* bitflags
+    pub created_at: i64,
+    pub version: u8,
+    pub treasurer: Pubkey,
+    pub operators: Vec<Pubkey>, // at most MAX_OPERATORS
+    pub withdrawal_delay: i64,  // seconds between queue_withdrawal and execution
+    pub open_deposits: bool,    // anyone may deposit, not only operators
+    pub total_sol_deposited: u64,
+    pub game_id: u64,              // game PDA seed alongside creator
+    pub instant_prize_limit: u64,  // largest prize the oracle callback pays directly, in lamports
+    pub operator_max_rtp_bps: u16, // highest RTP operators may configure; 0 keeps boxes owner-only
+}
+
+// Game layout as deployed before per-mint vaults.
+#[derive(AnchorDeserialize)]
+struct LegacyGame {
+    bump: u8,
+    authority: Pubkey,
+    token_address: Pubkey,
+    #[allow(dead_code)]
+    default_multiplier: u8,
+    created_at: i64,
+}
+
+// Player layout as deployed before records were tied to a game.
+#[derive(AnchorDeserialize)]
+struct LegacyPlayer {
+    bump: u8,
+    authority: Pubkey,
+    claimed: bool,
+    created_at: i64,
+    win_amount: u64,
+    token_type: String, // "sol" or "token"
+    identifier: String,
+}
+
+#[account]
+pub struct TokenVault {
+    pub bump: u8,
+    pub game: Pubkey,
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub enabled: bool,
+    pub liabilities: u64,
+    pub total_deposited: u64,
+    pub instant_prize_limit: u64, // largest prize the oracle callback pays directly
+}
+
+#[account]
+pub struct PlayBatch {
+    pub bump: u8,
+    pub authority: Pubkey,
+    pub game: Pubkey,
+    pub box_config: Pubkey,
+    pub status: PlayerStatus,
+    pub randomness_source: RandomnessSource,
+    pub created_at: i64,
+    pub count: u8,
+    pub prize_indices: Vec<u8>, // one per box once revealed
+    pub win_amount: u64,        // sum over all boxes
+    pub wager: u64,
+    pub reserved: u64,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub commitment: [u8; 32],
+    pub commit_slot: u64,
+    pub prizes: PrizeTable, // box odds as sold, settled against even if the box changes
+}
+
+#[account]
+pub struct PendingWithdrawal {
+    pub bump: u8,
+    pub game: Pubkey,
+    pub withdrawal_id: u64,
+    pub currency: Currency,
+    pub mint: Pubkey,        // default for SOL withdrawals
+    pub amount: Option<u64>, // base units, None withdraws the whole surplus
+    pub destination: Pubkey, // wallet for SOL, token account for tokens
+    pub unlock_at: i64,
+    pub created_at: i64,
+}
+
+#[account]
+pub struct PendingTreasuryChange {
+    pub bump: u8,
+    pub game: Pubkey,
+    pub treasurer: Pubkey,
+    pub withdrawal_delay: i64,
+    pub unlock_at: i64,
+    pub created_at: i64,
+}
+
+// Lifetime statistics of a wallet in a game. The nonce seeds its next
+// Player record.
+#[account]
+pub struct PlayerProfile {
+    pub bump: u8,
+    pub game: Pubkey,
+    pub authority: Pubkey,
+    pub nonce: u64,
+    pub last_play_at: i64,
+    pub sol: CurrencyStats,
+    pub tokens: Vec<MintStats>, // at most MAX_PROFILE_MINTS, in each mint's base units
+    pub box_opens: Vec<BoxOpens>, // at most MAX_PROFILE_BOXES
+}
+
+#[account]
+pub struct Player {
+    pub bump: u8,
+    pub authority: Pubkey,
+    pub game: Pubkey,
+    pub status: PlayerStatus,
+    pub randomness_source: RandomnessSource,
+    pub created_at: i64,
+    pub win_amount: u64,
+    pub wager: u64,
+    pub reserved: u64,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub identifier: String, // at most MAX_IDENTIFIER_LEN bytes
+    pub box_config: Pubkey,
+    pub commitment: [u8; 32],
+    pub commit_slot: u64,
+    pub prizes: PrizeTable, // box odds as sold, settled against even if the box changes
+    pub auto_claim: bool,   // paid by the oracle callback when within the instant limit
+}
+
+#[account]
+pub struct BoxConfig {
+    pub bump: u8,
+    pub game: Pubkey,
+    pub box_id: u8,
+    pub currency: Currency,
+    pub mint: Pubkey, // default for SOL boxes
+    pub price: u64,   // lamports or token base units
+    pub denominator: u64,
+    pub chances: Vec<u64>,
+    pub win_values: Vec<u64>, // same unit as price
+    pub retired: bool,
+}
+
+impl Game {
+    // The owner can always act as an operator.
+    fn is_operator(&self, key: &Pubkey) -> bool {
+        self.authority == *key || self.operators.contains(key)
+    }
+
+    // A version 3 game has not stored a limit yet, whatever its padding says.
+    fn operator_rtp_limit(&self) -> u16 {
+        if self.version == GAME_VERSION {
+            self.operator_max_rtp_bps
+        } else {
+            0
+        }
+    }
+}
+
+impl PlayBatch {
+    fn stake(&mut self) -> Stake<'_> {
+        Stake {
+            game: self.game,
+            authority: self.authority,
+            currency: self.currency,
+            mint: self.mint,
+            randomness_source: self.randomness_source,
+            commit_slot: self.commit_slot,
+            wager: self.wager,
+            status: &mut self.status,
+            win_amount: &mut self.win_amount,
+            reserved: &mut self.reserved,
+        }
+    }
+
+    fn settle(
+        &mut self,
+        game: &mut Game,
+        token_vault: Option<&mut TokenVault>,
+        box_config: &BoxConfig,
+        randomness: &[u8; 32],
+    ) -> Result<()> {
+        let price = self.wager / self.count as u64;
+        let mut win_amount: u64 = 0;
+        for i in 0..self.count {
+            // Each box draws from its own hash of the shared randomness.
+            let box_randomness = hashv(&[randomness, &[i]]).to_bytes();
+            let (rand_num, prize_index) = draw(&self.prizes, &box_randomness);
+            let prize_amount = self.prizes.win_values[prize_index];
+            win_amount = win_amount
+                .checked_add(prize_amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            self.prize_indices.push(prize_index as u8);
+
+            emit!(BoxOpened {
+                game: self.game,
+                player: self.authority,
+                box_config: self.box_config,
+                box_id: box_config.box_id,
+                currency: self.currency,
+                mint: self.mint,
+                price,
+                roll: rand_num,
+                prize_index: prize_index as u8,
+                prize_amount,
+            });
+        }
+
+        self.stake()
+            .resolve(PlayerStatus::Revealed, win_amount, game, token_vault)
+    }
+}
+
+impl PlayerProfile {
+    fn init_if_new(&mut self, bump: u8, game: Pubkey, authority: Pubkey) {
+        if self.authority == Pubkey::default() {
+            self.bump = bump;
+            self.game = game;
+            self.authority = authority;
+        }
+    }
+
+    // Mints past MAX_PROFILE_MINTS are still playable, just not counted.
+    fn stats_mut(&mut self, currency: Currency, mint: Pubkey) -> Option<&mut CurrencyStats> {
+        match currency {
+            Currency::Sol => Some(&mut self.sol),
+            Currency::Token => {
+                if let Some(i) = self.tokens.iter().position(|t| t.mint == mint) {
+                    Some(&mut self.tokens[i].stats)
+                } else if self.tokens.len() < MAX_PROFILE_MINTS {
+                    self.tokens.push(MintStats {
+                        mint,
+                        stats: CurrencyStats::default(),
+                    });
+                    self.tokens.last_mut().map(|t| &mut t.stats)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn record_play(
+        &mut self,
+        currency: Currency,
+        mint: Pubkey,
+        box_id: u8,
+        opens: u64,
+        wager: u64,
+    ) -> Result<()> {
+        if let Some(stats) = self.stats_mut(currency, mint) {
+            stats.wagered = stats
+                .wagered
+                .checked_add(wager)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        // Boxes past MAX_PROFILE_BOXES are still playable, just not counted.
+        if let Some(b) = self.box_opens.iter_mut().find(|b| b.box_id == box_id) {
+            b.opens = b.opens.checked_add(opens).ok_or(ErrorCode::MathOverflow)?;
+        } else if self.box_opens.len() < MAX_PROFILE_BOXES {
+            self.box_opens.push(BoxOpens { box_id, opens });
+        }
+
+        self.last_play_at = Clock::get()?.unix_timestamp;
+        self.nonce = self.nonce.checked_add(1).ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    fn record_win(&mut self, currency: Currency, mint: Pubkey, amount: u64) -> Result<()> {
+        if let Some(stats) = self.stats_mut(currency, mint) {
+            stats.won = stats
+                .won
+                .checked_add(amount)
+                .ok_or(ErrorCode::MathOverflow)?;
+            stats.biggest_win = stats.biggest_win.max(amount);
+        }
+        Ok(())
+    }
+}
+
+impl BoxConfig {
+    // Boxes carry their whole prize table in the event, so odds can be
+    // rebuilt from logs alone.
+    fn emit_configured(&self) {
+        emit!(BoxConfigured {
+            game: self.game,
+            box_id: self.box_id,
+            currency: self.currency,
+            mint: self.mint,
+            price: self.price,
+            denominator: self.denominator,
+            chances: self.chances.clone(),
+            win_values: self.win_values.clone(),
+        });
+    }
+
+    fn prize_table(&self) -> PrizeTable {
+        PrizeTable {
+            denominator: self.denominator,
+            chances: self.chances.clone(),
+            win_values: self.win_values.clone(),
+        }
+    }
+}
+
+impl PrizeTable {
+    fn max_prize(&self) -> u64 {
+        self.win_values.iter().copied().max().unwrap_or(0)
+    }
+}
+
+impl Player {
+    fn stake(&mut self) -> Stake<'_> {
+        Stake {
+            game: self.game,
+            authority: self.authority,
+            currency: self.currency,
+            mint: self.mint,
+            randomness_source: self.randomness_source,
+            commit_slot: self.commit_slot,
+            wager: self.wager,
+            status: &mut self.status,
+            win_amount: &mut self.win_amount,
+            reserved: &mut self.reserved,
+        }
+    }
+
+    fn settle(
+        &mut self,
+        game: &mut Game,
+        token_vault: Option<&mut TokenVault>,
+        box_config: &BoxConfig,
+        randomness: &[u8; 32],
+    ) -> Result<()> {
+        let (rand_num, prize_index) = draw(&self.prizes, randomness);
+        let prize_amount = self.prizes.win_values[prize_index];
+        self.stake()
+            .resolve(PlayerStatus::Revealed, prize_amount, game, token_vault)?;
+
+        emit!(BoxOpened {
+            game: self.game,
+            player: self.authority,
+            box_config: self.box_config,
+            box_id: box_config.box_id,
+            currency: self.currency,
+            mint: self.mint,
+            price: self.wager,
+            roll: rand_num,
+            prize_index: prize_index as u8,
+            prize_amount,
+        });
+
+        Ok(())
+    }
+}
+
+// The part of a Player or PlayBatch that is held against the game's
+// liabilities, so both settle, expire and pay out through the same code.
+struct Stake<'a> {
+    game: Pubkey,
+    authority: Pubkey,
+    currency: Currency,
+    mint: Pubkey,
+    randomness_source: RandomnessSource,
+    commit_slot: u64,
+    wager: u64,
+    status: &'a mut PlayerStatus,
+    win_amount: &'a mut u64,
+    reserved: &'a mut u64,
+}
+
+impl Stake<'_> {
+    // Swaps the reservation for what the play actually owes. Plays reserve
+    // the larger of their worst prize and their refund from the prize table
+    // they were sold with, so this can only ever release.
+    fn resolve(
+        &mut self,
+        status: PlayerStatus,
+        win_amount: u64,
+        game: &mut Game,
+        token_vault: Option<&mut TokenVault>,
+    ) -> Result<()> {
+        let liabilities = match self.currency {
+            Currency::Sol => &mut game.sol_liabilities,
+            Currency::Token => &mut token_vault.ok_or(ErrorCode::MissingTokenVault)?.liabilities,
+        };
+        let released = self
+            .reserved
+            .checked_sub(win_amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        sub_liability(liabilities, released)?;
+
+        *self.reserved = win_amount;
+        *self.win_amount = win_amount;
+        *self.status = status;
+
+        Ok(())
+    }
+
+    fn expire(&mut self, game: &mut Game, token_vault: Option<&mut TokenVault>) -> Result<()> {
+        let refund = expiry_refund(self.randomness_source, self.commit_slot, self.wager)?;
+        self.resolve(PlayerStatus::Expired, refund, game, token_vault)?;
+
+        emit!(PlayExpired {
+            game: self.game,
+            player: self.authority,
+            currency: self.currency,
+            mint: self.mint,
+            refund,
+        });
+
+        Ok(())
+    }
+
+    // Takes the win off the game's liabilities and marks the play claimed,
+    // returning the amount the caller has to transfer. An expired play only
+    // gets its wager back, which is not a prize.
+    fn claim(
+        &mut self,
+        liabilities: &mut u64,
+        player_profile: Option<&mut PlayerProfile>,
+    ) -> Result<u64> {
+        let amount = *self.win_amount;
+        sub_liability(liabilities, amount)?;
+
+        if *self.status == PlayerStatus::Expired {
+            emit!(RefundClaimed {
+                game: self.game,
+                player: self.authority,
+                currency: self.currency,
+                mint: self.mint,
+                amount,
+            });
+        } else {
+            emit!(RewardClaimed {
+                game: self.game,
+                player: self.authority,
+                currency: self.currency,
+                mint: self.mint,
+                amount,
+            });
+
+            if let Some(player_profile) = player_profile {
+                player_profile.record_win(self.currency, self.mint, amount)?;
+            }
+        }
+
+        *self.status = PlayerStatus::Claimed;
+        *self.win_amount = 0;
+
+        Ok(amount)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct PrizeTable {
+    pub denominator: u64,
+    pub chances: Vec<u64>,    // at most MAX_PRIZES entries
+    pub win_values: Vec<u64>, // same unit as the box price
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default)]
+pub struct CurrencyStats {
+    pub wagered: u64,
+    pub won: u64,
+    pub biggest_win: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintStats {
+    pub mint: Pubkey,
+    pub stats: CurrencyStats,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BoxOpens {
+    pub box_id: u8,
+    pub opens: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PlayerStatus {
+    Pending,
+    Revealed,
+    Claimed,
+    Expired, // never settled, win_amount holds the refund
+}
+
+impl PlayerStatus {
+    // Settled plays, won or expired, can be claimed.
+    fn is_settled(self) -> bool {
+        self == PlayerStatus::Revealed || self == PlayerStatus::Expired
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Currency {
+    Sol,
+    Token,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum RandomnessSource {
+    CommitReveal,
+    Oracle,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct InitGameIx {
+    pub game_id: u64,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct ProposeAuthorityIx {
+    pub new_authority: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct OperatorIx {
+    pub operator: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct OperatorRtpLimitIx {
+    pub max_rtp_bps: u16,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct TreasuryChangeIx {
+    pub treasurer: Pubkey,
+    pub withdrawal_delay: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetOpenDepositsIx {
+    pub open: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct DepositIx {
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetPauseIx {
+    pub paused: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct AddTokenIx {
+    pub default_multiplier: u8,
+    pub amount: u64, // token base units
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct PlayIx {
+    pub box_id: u8,
+    pub identifier: String,
+    pub commitment: [u8; 32],
+    // Oracle games only: the fulfill callback pays the prize in the same
+    // transaction instead of leaving it for claim_reward_*. The oracle must pass
+    // authority, player_profile, game_token_account, authority_token_account,
+    // mint and token_program (the program id for slots a SOL play leaves out).
+    // Prizes above the instant prize limit are left for the usual claim.
+    pub auto_claim: bool,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct PlayBatchIx {
+    pub box_id: u8,
+    pub count: u8,
+    pub commitment: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct CreateBoxIx {
+    pub box_id: u8,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub denominator: u64,
+    pub chances: Vec<u64>,
+    pub win_values: Vec<u64>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct UpdateBoxIx {
+    pub price: u64,
+    pub denominator: u64,
+    pub chances: Vec<u64>,
+    pub win_values: Vec<u64>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct RevealIx {
+    pub seed: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct FulfillRandomnessIx {
+    pub randomness: [u8; 32],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct QueueWithdrawalIx {
+    pub withdrawal_id: u64,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub amount: Option<u64>,
+    pub destination: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetInstantPrizeLimitIx {
+    pub limit: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct SetRandomnessSourceIx {
+    pub source: RandomnessSource,
+    pub oracle: Pubkey,
+}
+
+#[event]
+pub struct GameInitialized {
+    pub game: Pubkey,
+    pub creator: Pubkey,
+    pub game_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct GameMigrated {
+    pub game: Pubkey,
+    pub version: u8,
+}
+
+#[event]
+pub struct AuthorityProposed {
+    pub game: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub game: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct OperatorAdded {
+    pub game: Pubkey,
+    pub operator: Pubkey,
+}
+
+#[event]
+pub struct OperatorRemoved {
+    pub game: Pubkey,
+    pub operator: Pubkey,
+}
+
+#[event]
+pub struct OperatorRtpLimitChanged {
+    pub game: Pubkey,
+    pub max_rtp_bps: u16,
+}
+
+#[event]
+pub struct PauseChanged {
+    pub game: Pubkey,
+    pub paused: u8, // PAUSE_* bitflags
+}
+
+#[event]
+pub struct OpenDepositsChanged {
+    pub game: Pubkey,
+    pub open: bool,
+}
+
+#[event]
+pub struct RandomnessSourceChanged {
+    pub game: Pubkey,
+    pub source: RandomnessSource,
+    pub oracle: Pubkey,
+}
+
+#[event]
+pub struct InstantPrizeLimitChanged {
+    pub game: Pubkey,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub limit: u64,
+}
+
+#[event]
+pub struct TokenAdded {
+    pub game: Pubkey,
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TokenDisabled {
+    pub game: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct TokenRemoved {
+    pub game: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct BoxConfigured {
+    pub game: Pubkey,
+    pub box_id: u8,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub price: u64,
+    pub denominator: u64,
+    pub chances: Vec<u64>,
+    pub win_values: Vec<u64>,
+}
+
+#[event]
+pub struct BoxRetired {
+    pub game: Pubkey,
+    pub box_config: Pubkey,
+    pub box_id: u8,
+}
+
+#[event]
+pub struct PlayStarted {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub play: Pubkey, // Player or PlayBatch account
+    pub box_id: u8,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub count: u8,
+    pub wager: u64,
+    pub reserved: u64,
+}
+
+#[event]
+pub struct BoxOpened {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub box_config: Pubkey,
+    pub box_id: u8,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub price: u64, // amount actually received for the play
+    pub roll: u64,
+    pub prize_index: u8,
+    pub prize_amount: u64,
+}
+
+#[event]
+pub struct RewardClaimed {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PlayExpired {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub refund: u64,
+}
+
+#[event]
+pub struct RefundClaimed {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PlayClosed {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub play: Pubkey,
+}
+
+#[event]
+pub struct PlayerMigrated {
+    pub game: Pubkey,
+    pub player: Pubkey,
+    pub play: Pubkey,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub win_amount: u64,
+}
+
+#[event]
+pub struct Deposited {
+    pub game: Pubkey,
+    pub depositor: Pubkey,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WithdrawalQueued {
+    pub game: Pubkey,
+    pub withdrawal_id: u64,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub amount: Option<u64>,
+    pub destination: Pubkey,
+    pub unlock_at: i64,
+}
+
+#[event]
+pub struct WithdrawalCancelled {
+    pub game: Pubkey,
+    pub withdrawal_id: u64,
+}
+
+#[event]
+pub struct TreasuryChangeQueued {
+    pub game: Pubkey,
+    pub treasurer: Pubkey,
+    pub withdrawal_delay: i64,
+    pub unlock_at: i64,
+}
+
+#[event]
+pub struct TreasuryChangeCancelled {
+    pub game: Pubkey,
+}
+
+#[event]
+pub struct TreasuryChanged {
+    pub game: Pubkey,
+    pub treasurer: Pubkey,
+    pub withdrawal_delay: i64,
+}
+
+#[event]
+pub struct Withdrawn {
+    pub game: Pubkey,
+    pub withdrawal_id: u64,
+    pub currency: Currency,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Invalid Admin")]
+    InvalidAdmin,
+    #[msg("Token Already Added")]
+    TokenAlreadyAdded,
+    #[msg("Seed Does Not Match Commitment")]
+    InvalidReveal,
+    #[msg("Slot Hash Not Available")]
+    SlotHashNotFound,
+    #[msg("Play Already Revealed")]
+    AlreadyRevealed,
+    #[msg("Play Not Revealed")]
+    NotRevealed,
+    #[msg("Invalid Oracle")]
+    InvalidOracle,
+    #[msg("Wrong Randomness Source")]
+    WrongRandomnessSource,
+    #[msg("Too Many Prizes")]
+    TooManyPrizes,
+    #[msg("Box Retired")]
+    BoxRetired,
+    #[msg("Wrong Currency")]
+    WrongCurrency,
+    #[msg("Invalid Box")]
+    InvalidBox,
+    #[msg("Empty Prize Table")]
+    EmptyPrizeTable,
+    #[msg("Prize Table Length Mismatch")]
+    PrizeTableLengthMismatch,
+    #[msg("Odds Do Not Sum To Denominator")]
+    InvalidOddsTotal,
+    #[msg("Invalid Box Type")]
+    InvalidBoxType,
+    #[msg("Math Overflow")]
+    MathOverflow,
+    #[msg("Invalid Game")]
+    InvalidGame,
+    #[msg("Invalid Player")]
+    InvalidPlayer,
+    #[msg("Reward Already Claimed")]
+    AlreadyClaimed,
+    #[msg("Reward Not Claimed")]
+    UnclaimedReward,
+    #[msg("Insufficient Bankroll")]
+    InsufficientBankroll,
+    #[msg("Insufficient Surplus")]
+    InsufficientSurplus,
+    #[msg("Invalid Mint")]
+    InvalidMint,
+    #[msg("Token Disabled")]
+    TokenDisabled,
+    #[msg("Token Vault Required")]
+    MissingTokenVault,
+    #[msg("Outstanding Liabilities")]
+    OutstandingLiabilities,
+    #[msg("Vault Not Empty")]
+    VaultNotEmpty,
+    #[msg("Decimals Do Not Match Mint")]
+    InvalidDecimals,
+    #[msg("Game Already Migrated")]
+    AlreadyMigrated,
+    #[msg("Invalid Pending Authority")]
+    InvalidPendingAuthority,
+    #[msg("Invalid Pause Flags")]
+    InvalidPauseFlags,
+    #[msg("Game Paused")]
+    GamePaused,
+    #[msg("Invalid Operator")]
+    InvalidOperator,
+    #[msg("Invalid Treasurer")]
+    InvalidTreasurer,
+    #[msg("Too Many Operators")]
+    TooManyOperators,
+    #[msg("Operator Already Added")]
+    OperatorAlreadyAdded,
+    #[msg("Operator Not Found")]
+    OperatorNotFound,
+    #[msg("Invalid Withdrawal Delay")]
+    InvalidWithdrawalDelay,
+    #[msg("Withdrawal Locked")]
+    WithdrawalLocked,
+    #[msg("Invalid Destination")]
+    InvalidDestination,
+    #[msg("Deposits Closed")]
+    DepositsClosed,
+    #[msg("Identifier Too Long")]
+    IdentifierTooLong,
+    #[msg("Invalid Batch Size")]
+    InvalidBatchSize,
+    #[msg("Auto Claim Requires Oracle")]
+    AutoClaimRequiresOracle,
+    #[msg("Play Not Expired")]
+    PlayNotExpired,
+    #[msg("Timelock Expired")]
+    TimelockExpired,
+    #[msg("Invalid Rtp Limit")]
+    InvalidRtpLimit,
+    #[msg("Box Exceeds Operator Limit")]
+    BoxExceedsOperatorLimit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(chances: &[u64]) -> PrizeTable {
+        PrizeTable {
+            denominator: chances.iter().sum(),
+            chances: chances.to_vec(),
+            win_values: vec![0; chances.len()],
+        }
+    }
+
+    fn randomness_for(roll: u128) -> [u8; 32] {
+        let mut randomness = [0u8; 32];
+        randomness[..16].copy_from_slice(&roll.to_le_bytes());
+        randomness
+    }
+
+    #[test]
+    fn pick_index_boundaries() {
+        let chances = [10, 20, 30];
+        assert_eq!(pick_index(&chances, 0), 0);
+        assert_eq!(pick_index(&chances, 9), 0);
+        // A roll equal to a cumulative boundary belongs to the next prize.
+        assert_eq!(pick_index(&chances, 10), 1);
+        assert_eq!(pick_index(&chances, 29), 1);
+        assert_eq!(pick_index(&chances, 30), 2);
+        assert_eq!(pick_index(&chances, 59), 2);
+    }
+
+    #[test]
+    fn pick_index_skips_zero_chances() {
+        assert_eq!(pick_index(&[0, 5, 0, 5], 0), 1);
+        assert_eq!(pick_index(&[0, 5, 0, 5], 5), 3);
+    }
+
+    #[test]
+    fn draw_stays_below_denominator() {
+        let prizes = table(&[1, 2, 3]);
+        assert_eq!(draw(&prizes, &randomness_for(5)), (5, 2));
+        assert_eq!(draw(&prizes, &randomness_for(6)), (0, 0));
+        assert_eq!(draw(&prizes, &randomness_for(u128::MAX)), (3, 2));
+    }
+
+    #[test]
+    fn validate_prize_table_accepts_matching_odds() {
+        assert!(validate_prize_table(100, &[60, 40], &[0, 5]).is_ok());
+        assert!(validate_prize_table(10, &[1; MAX_PRIZES], &[1; MAX_PRIZES]).is_ok());
+    }
+
+    #[test]
+    fn validate_prize_table_rejects_bad_tables() {
+        let rejects = |denominator: u64, chances: &[u64], win_values: &[u64], code: ErrorCode| {
+            assert_eq!(
+                validate_prize_table(denominator, chances, win_values).unwrap_err(),
+                code.into()
+            );
+        };
+
+        rejects(0, &[], &[], ErrorCode::EmptyPrizeTable);
+        rejects(11, &[1; 11], &[1; 11], ErrorCode::TooManyPrizes);
+        rejects(10, &[5, 5], &[1], ErrorCode::PrizeTableLengthMismatch);
+        rejects(10, &[5, 4], &[1, 2], ErrorCode::InvalidOddsTotal);
+        rejects(0, &[0, 0], &[1, 2], ErrorCode::InvalidOddsTotal);
+        rejects(1, &[u64::MAX, 2], &[1, 2], ErrorCode::InvalidOddsTotal);
+    }
+
+    #[test]
+    fn check_operator_rtp_bounds_expected_payout() {
+        // 50% chance of 180 on a price of 100 is a 90% RTP.
+        assert!(check_operator_rtp(9_000, 100, 10, &[5, 5], &[0, 180]).is_ok());
+        assert_eq!(
+            check_operator_rtp(8_999, 100, 10, &[5, 5], &[0, 180]).unwrap_err(),
+            ErrorCode::BoxExceedsOperatorLimit.into()
+        );
+        assert_eq!(
+            check_operator_rtp(0, 100, 10, &[5, 5], &[0, 1]).unwrap_err(),
+            ErrorCode::BoxExceedsOperatorLimit.into()
+        );
+        assert!(check_operator_rtp(0, 100, 10, &[10], &[0]).is_ok());
+    }
+}
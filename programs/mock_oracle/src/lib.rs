@@ -0,0 +1,97 @@
+use anchor_lang::prelude::*;
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::invoke_signed;
+
+// Stand-in for a VRF oracle on a local validator. Point the lottery game at
+// this program's oracle PDA with `set_randomness_source`, then call `fulfill`
+// with whatever randomness the test wants to see drawn. Remaining accounts are
+// passed through to the callback after the oracle, which is how auto-claim
+// plays receive the accounts they are paid out with.
+declare_id!("EiFCqfGj5YQebi3WrQGowtXhhnNe77jiH87xc28T2TBU");
+
+pub const ORACLE_PREFIX: &str = "oracle";
+
+#[program]
+mod mock_oracle {
+    use super::*;
+    pub fn fulfill<'info>(
+        ctx: Context<'_, '_, '_, 'info, FulfillCtx<'info>>,
+        ix: FulfillIx,
+    ) -> Result<()> {
+        let name: &[u8] = if ix.batch {
+            b"global:fulfill_batch_randomness"
+        } else {
+            b"global:fulfill_randomness"
+        };
+        let mut data = hash(name).to_bytes()[..8].to_vec();
+        data.extend_from_slice(&ix.randomness);
+
+        let mut instruction = Instruction {
+            program_id: ctx.accounts.lottery_program.key(),
+            accounts: vec![
+                AccountMeta::new(ctx.accounts.player.key(), false),
+                AccountMeta::new(ctx.accounts.game.key(), false),
+                AccountMeta::new_readonly(ctx.accounts.box_config.key(), false),
+                // SOL plays pass the lottery program id in place of a vault
+                AccountMeta {
+                    pubkey: ctx.accounts.token_vault.key(),
+                    is_signer: false,
+                    is_writable: ctx.accounts.token_vault.is_writable,
+                },
+                AccountMeta::new_readonly(ctx.accounts.oracle.key(), true),
+            ],
+            data,
+        };
+
+        let mut account_infos = vec![
+            ctx.accounts.player.to_account_info(),
+            ctx.accounts.game.to_account_info(),
+            ctx.accounts.box_config.to_account_info(),
+            ctx.accounts.token_vault.to_account_info(),
+            ctx.accounts.oracle.to_account_info(),
+            ctx.accounts.lottery_program.to_account_info(),
+        ];
+        for account in ctx.remaining_accounts {
+            instruction.accounts.push(AccountMeta {
+                pubkey: account.key(),
+                is_signer: false,
+                is_writable: account.is_writable,
+            });
+            account_infos.push(account.clone());
+        }
+
+        let oracle_seeds = &[ORACLE_PREFIX.as_bytes(), &[ctx.bumps.oracle]];
+
+        invoke_signed(&instruction, &account_infos, &[&oracle_seeds[..]])?;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct FulfillCtx<'info> {
+    /// CHECK: Player or PlayBatch, validated by the lottery program
+    #[account(mut)]
+    player: UncheckedAccount<'info>,
+    /// CHECK: validated by the lottery program
+    #[account(mut)]
+    game: UncheckedAccount<'info>,
+    /// CHECK: validated by the lottery program
+    box_config: UncheckedAccount<'info>,
+    /// CHECK: validated by the lottery program
+    token_vault: UncheckedAccount<'info>,
+    /// CHECK: signer PDA registered as the game's oracle
+    #[account(seeds = [ORACLE_PREFIX.as_bytes()], bump)]
+    oracle: UncheckedAccount<'info>,
+    /// CHECK: callback target
+    #[account(executable)]
+    lottery_program: UncheckedAccount<'info>,
+    payer: Signer<'info>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct FulfillIx {
+    pub randomness: [u8; 32],
+    pub batch: bool, // settle a PlayBatch instead of a Player
+}
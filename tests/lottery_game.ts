@@ -0,0 +1,483 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program, BN } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+  SYSVAR_SLOT_HASHES_PUBKEY,
+} from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccount,
+  createMint,
+  getAssociatedTokenAddressSync,
+  mintTo,
+} from "@solana/spl-token";
+import { createHash } from "crypto";
+import { assert } from "chai";
+import { LotteryGame } from "../target/types/lottery_game";
+import { MockOracle } from "../target/types/mock_oracle";
+
+// Runs against `anchor test`, which starts a local validator and deploys both
+// workspace programs. Randomness comes either from the commit-reveal path or
+// from the mock oracle, so nothing here needs network access.
+describe("lottery_game", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.LotteryGame as Program<LotteryGame>;
+  const oracleProgram = anchor.workspace.MockOracle as Program<MockOracle>;
+  const payer = provider.wallet.publicKey;
+
+  const gameId = new BN(1);
+  const [game] = PublicKey.findProgramAddressSync(
+    [Buffer.from("game"), payer.toBuffer(), gameId.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  );
+  const [oracle] = PublicKey.findProgramAddressSync(
+    [Buffer.from("oracle")],
+    oracleProgram.programId
+  );
+  const [profile] = PublicKey.findProgramAddressSync(
+    [Buffer.from("profile"), game.toBuffer(), payer.toBuffer()],
+    program.programId
+  );
+
+  const boxPda = (boxId: number) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("box"), game.toBuffer(), Buffer.from([boxId])],
+      program.programId
+    )[0];
+
+  const nextPlayer = async () => {
+    const existing = await program.account.playerProfile.fetchNullable(profile);
+    const nonce = existing ? existing.nonce : new BN(0);
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("player"),
+        game.toBuffer(),
+        payer.toBuffer(),
+        nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+  };
+
+  const play = async (boxId: number, commitment: Buffer, autoClaim = false) => {
+    const player = await nextPlayer();
+    await program.methods
+      .playSol({
+        boxId,
+        identifier: "",
+        commitment: [...commitment],
+        autoClaim,
+      })
+      .accounts({
+        playerProfile: profile,
+        player,
+        game,
+        boxConfig: boxPda(boxId),
+        payer,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    return player;
+  };
+
+  const reveal = (player: PublicKey, seed: Buffer) =>
+    program.methods
+      .reveal({ seed: [...seed] })
+      .accounts({
+        player,
+        game,
+        boxConfig: boxPda(1),
+        tokenVault: null,
+        slotHashes: SYSVAR_SLOT_HASHES_PUBKEY,
+        payer,
+      })
+      .rpc();
+
+  const claimOrClose = async (player: PublicKey) => {
+    const settled = await program.account.player.fetch(player);
+    if (settled.winAmount.isZero()) {
+      await program.methods.closePlayer().accounts({ player, payer }).rpc();
+    } else {
+      await program.methods
+        .claimRewardSol()
+        .accounts({
+          player,
+          game,
+          playerProfile: profile,
+          payer,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+  };
+
+  const waitForNextSlot = async () => {
+    const start = await provider.connection.getSlot();
+    while ((await provider.connection.getSlot()) <= start) {
+      await new Promise((resolve) => setTimeout(resolve, 100));
+    }
+  };
+
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    try {
+      await promise;
+    } catch (err) {
+      assert.include(err.toString(), code);
+      return;
+    }
+    assert.fail(`expected ${code}`);
+  };
+
+  before(async () => {
+    await program.methods
+      .initGame({ gameId, amount: new BN(5 * LAMPORTS_PER_SOL) })
+      .accounts({ game, payer, systemProgram: SystemProgram.programId })
+      .rpc();
+
+    // Box 1 is a coin flip between nothing and 0.2 SOL, box 2 always pays
+    // 0.05 SOL so the oracle test knows the outcome up front.
+    const boxes = [
+      { boxId: 1, chances: [50, 50], winValues: [0, 0.2 * LAMPORTS_PER_SOL] },
+      { boxId: 2, chances: [100], winValues: [0.05 * LAMPORTS_PER_SOL] },
+    ];
+    for (const box of boxes) {
+      await program.methods
+        .createBox({
+          boxId: box.boxId,
+          currency: { sol: {} },
+          mint: PublicKey.default,
+          price: new BN(0.1 * LAMPORTS_PER_SOL),
+          denominator: new BN(100),
+          chances: box.chances.map((c) => new BN(c)),
+          winValues: box.winValues.map((w) => new BN(w)),
+        })
+        .accounts({
+          boxConfig: boxPda(box.boxId),
+          game,
+          payer,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    }
+  });
+
+  it("only lets oracle games auto-claim", async () => {
+    await expectError(
+      play(1, Buffer.alloc(32), true),
+      "AutoClaimRequiresOracle"
+    );
+  });
+
+  it("rejects a box id that was never created", async () => {
+    await expectError(play(99, Buffer.alloc(32)), "InvalidBoxType");
+  });
+
+  it("settles a commit-reveal play once its slot hash is known", async () => {
+    const seed = Keypair.generate().publicKey.toBuffer();
+    const commitment = createHash("sha256").update(seed).digest();
+    const player = await play(1, commitment);
+
+    await expectError(reveal(player, Buffer.alloc(32, 7)), "InvalidReveal");
+
+    await waitForNextSlot();
+    await reveal(player, seed);
+
+    const settled = await program.account.player.fetch(player);
+    assert.ok("revealed" in settled.status);
+    assert.include([0, 0.2 * LAMPORTS_PER_SOL], settled.winAmount.toNumber());
+
+    await claimOrClose(player);
+    assert.isNull(await program.account.player.fetchNullable(player));
+
+    const state = await program.account.game.fetch(game);
+    assert.equal(state.solLiabilities.toNumber(), 0);
+  });
+
+  it("keeps a pending play until its slot hash leaves SlotHashes", async () => {
+    const seed = Keypair.generate().publicKey.toBuffer();
+    const player = await play(1, createHash("sha256").update(seed).digest());
+
+    await expectError(
+      program.methods
+        .expirePlay()
+        .accounts({ player, game, tokenVault: null, payer })
+        .rpc(),
+      "PlayNotExpired"
+    );
+
+    await waitForNextSlot();
+    await reveal(player, seed);
+    await claimOrClose(player);
+  });
+
+  it("settles an oracle play only from the registered oracle", async () => {
+    await program.methods
+      .setRandomnessSource({ source: { oracle: {} }, oracle })
+      .accounts({ game, payer })
+      .rpc();
+
+    const player = await play(2, Buffer.alloc(32));
+
+    // Raising the prize after the play must not change what it pays.
+    await program.methods
+      .updateBox({
+        price: new BN(0.1 * LAMPORTS_PER_SOL),
+        denominator: new BN(100),
+        chances: [new BN(100)],
+        winValues: [new BN(0.5 * LAMPORTS_PER_SOL)],
+      })
+      .accounts({ boxConfig: boxPda(2), game, payer })
+      .rpc();
+
+    const randomness = [...Keypair.generate().publicKey.toBuffer()];
+
+    const impostor = Keypair.generate();
+    await expectError(
+      program.methods
+        .fulfillRandomness({ randomness })
+        .accounts({
+          player,
+          game,
+          boxConfig: boxPda(2),
+          tokenVault: null,
+          oracle: impostor.publicKey,
+        })
+        .signers([impostor])
+        .rpc(),
+      "InvalidOracle"
+    );
+
+    await oracleProgram.methods
+      .fulfill({ randomness, batch: false })
+      .accounts({
+        player,
+        game,
+        boxConfig: boxPda(2),
+        tokenVault: program.programId,
+        oracle,
+        lotteryProgram: program.programId,
+        payer,
+      })
+      .rpc();
+
+    const settled = await program.account.player.fetch(player);
+    assert.ok("revealed" in settled.status);
+    assert.equal(settled.winAmount.toNumber(), 0.05 * LAMPORTS_PER_SOL);
+
+    const before = await provider.connection.getBalance(payer);
+    await program.methods
+      .claimRewardSol()
+      .accounts({
+        player,
+        game,
+        playerProfile: profile,
+        payer,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    assert.isAbove(await provider.connection.getBalance(payer), before);
+
+    const state = await program.account.game.fetch(game);
+    assert.equal(state.solLiabilities.toNumber(), 0);
+  });
+
+  it("pays auto-claim plays from the oracle callback", async () => {
+    await program.methods
+      .setInstantPrizeLimit({ limit: new BN(LAMPORTS_PER_SOL) })
+      .accounts({ game, tokenVault: null, payer })
+      .rpc();
+
+    const player = await play(2, Buffer.alloc(32), true);
+    const before = await provider.connection.getBalance(payer);
+
+    // The payout accounts follow the oracle; token slots are left empty.
+    await oracleProgram.methods
+      .fulfill({ randomness: [...Buffer.alloc(32, 1)], batch: false })
+      .accounts({
+        player,
+        game,
+        boxConfig: boxPda(2),
+        tokenVault: program.programId,
+        oracle,
+        lotteryProgram: program.programId,
+        payer,
+      })
+      .remainingAccounts([
+        { pubkey: payer, isSigner: false, isWritable: true },
+        { pubkey: profile, isSigner: false, isWritable: true },
+        ...Array(4).fill({
+          pubkey: program.programId,
+          isSigner: false,
+          isWritable: false,
+        }),
+      ])
+      .rpc();
+
+    assert.isNull(await program.account.player.fetchNullable(player));
+    assert.isAbove(await provider.connection.getBalance(payer), before);
+
+    const state = await program.account.game.fetch(game);
+    assert.equal(state.solLiabilities.toNumber(), 0);
+  });
+
+  it("pays token auto-claim plays from the oracle callback", async () => {
+    const wallet = (provider.wallet as anchor.Wallet).payer;
+    const mint = await createMint(provider.connection, wallet, payer, null, 6);
+    const payerTokenAccount = await createAssociatedTokenAccount(
+      provider.connection,
+      wallet,
+      mint,
+      payer
+    );
+    await mintTo(
+      provider.connection,
+      wallet,
+      mint,
+      payerTokenAccount,
+      wallet,
+      1_000_000_000
+    );
+    const gameTokenAccount = getAssociatedTokenAddressSync(mint, game, true);
+    const [tokenVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), game.toBuffer(), mint.toBuffer()],
+      program.programId
+    );
+
+    await program.methods
+      .addToken({ defaultMultiplier: 6, amount: new BN(500_000_000) })
+      .accounts({
+        game,
+        tokenVault,
+        gameTokenAccount,
+        mint,
+        payerTokenAccount,
+        payer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    // Box 3 always pays 5 tokens on a 10 token price.
+    await program.methods
+      .createBox({
+        boxId: 3,
+        currency: { token: {} },
+        mint,
+        price: new BN(10_000_000),
+        denominator: new BN(100),
+        chances: [new BN(100)],
+        winValues: [new BN(5_000_000)],
+      })
+      .accounts({
+        boxConfig: boxPda(3),
+        game,
+        payer,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .setInstantPrizeLimit({ limit: new BN(10_000_000) })
+      .accounts({ game, tokenVault, payer })
+      .rpc();
+
+    const player = await nextPlayer();
+    await program.methods
+      .playToken({
+        boxId: 3,
+        identifier: "",
+        commitment: [...Buffer.alloc(32)],
+        autoClaim: true,
+      })
+      .accounts({
+        playerProfile: profile,
+        player,
+        game,
+        boxConfig: boxPda(3),
+        tokenVault,
+        gameTokenAccount,
+        mint,
+        payerTokenAccount,
+        payer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    const balance = async () =>
+      Number(
+        (await provider.connection.getTokenAccountBalance(payerTokenAccount))
+          .value.amount
+      );
+    const before = await balance();
+
+    await oracleProgram.methods
+      .fulfill({ randomness: [...Buffer.alloc(32, 1)], batch: false })
+      .accounts({
+        player,
+        game,
+        boxConfig: boxPda(3),
+        tokenVault,
+        oracle,
+        lotteryProgram: program.programId,
+        payer,
+      })
+      .remainingAccounts([
+        { pubkey: payer, isSigner: false, isWritable: true },
+        { pubkey: profile, isSigner: false, isWritable: true },
+        { pubkey: gameTokenAccount, isSigner: false, isWritable: true },
+        { pubkey: payerTokenAccount, isSigner: false, isWritable: true },
+        { pubkey: mint, isSigner: false, isWritable: false },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      ])
+      .rpc();
+
+    assert.isNull(await program.account.player.fetchNullable(player));
+    assert.equal((await balance()) - before, 5_000_000);
+
+    const vault = await program.account.tokenVault.fetch(tokenVault);
+    assert.equal(vault.liabilities.toNumber(), 0);
+  });
+
+  it("holds operators to the owner's RTP limit", async () => {
+    const operator = Keypair.generate();
+    await program.methods
+      .addOperator({ operator: operator.publicKey })
+      .accounts({ game, payer })
+      .rpc();
+
+    // 0.09 SOL on every 0.1 SOL play is a 90% RTP.
+    const updateBox = () =>
+      program.methods
+        .updateBox({
+          price: new BN(0.1 * LAMPORTS_PER_SOL),
+          denominator: new BN(100),
+          chances: [new BN(100)],
+          winValues: [new BN(0.09 * LAMPORTS_PER_SOL)],
+        })
+        .accounts({ boxConfig: boxPda(2), game, payer: operator.publicKey })
+        .signers([operator])
+        .rpc();
+
+    await expectError(updateBox(), "BoxExceedsOperatorLimit");
+
+    await program.methods
+      .setOperatorRtpLimit({ maxRtpBps: 9_000 })
+      .accounts({ game, payer })
+      .rpc();
+    await updateBox();
+
+    await program.methods
+      .removeOperator({ operator: operator.publicKey })
+      .accounts({ game, payer })
+      .rpc();
+  });
+});
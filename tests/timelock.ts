@@ -0,0 +1,331 @@
+import { Program, BN } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+  Transaction,
+} from "@solana/web3.js";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  AccountLayout,
+  MINT_SIZE,
+  TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccountIdempotentInstruction,
+  createInitializeMint2Instruction,
+  createMintToInstruction,
+  getAssociatedTokenAddressSync,
+} from "@solana/spl-token";
+import { BankrunProvider } from "anchor-bankrun";
+import { Clock, ProgramTestContext, startAnchor } from "solana-bankrun";
+import { assert } from "chai";
+import { IDL, LotteryGame } from "../target/types/lottery_game";
+
+// Withdrawals and treasury changes wait a day before they unlock, so these run
+// in bankrun, which can move the clock instead of waiting on a validator.
+describe("timelocks", () => {
+  const PROGRAM_ID = new PublicKey(
+    "FR87ZA1sCVabEMe21X24WkaBqqWdMne18UzGgKdQb4pE"
+  );
+  const DAY = 24 * 60 * 60;
+  const WINDOW = 3 * DAY;
+  const GAME_SPACE = 400;
+
+  let context: ProgramTestContext;
+  let program: Program<LotteryGame>;
+  let payer: PublicKey;
+  let game: PublicKey;
+
+  const withdrawalPda = (id: number) =>
+    PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("withdrawal"),
+        game.toBuffer(),
+        new BN(id).toArrayLike(Buffer, "le", 8),
+      ],
+      PROGRAM_ID
+    )[0];
+
+  const treasuryChangePda = () =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury_change"), game.toBuffer()],
+      PROGRAM_ID
+    )[0];
+
+  // Moving to a new slot as well gives later transactions a fresh blockhash,
+  // so retrying an identical instruction is not rejected as a duplicate. The
+  // time is taken from before the warp, which may recompute it.
+  const warp = async (seconds: number) => {
+    const before = await context.banksClient.getClock();
+    context.warpToSlot(before.slot + BigInt(1));
+    const clock = await context.banksClient.getClock();
+    context.setClock(
+      new Clock(
+        clock.slot,
+        clock.epochStartTimestamp,
+        clock.epoch,
+        clock.leaderScheduleEpoch,
+        before.unixTimestamp + BigInt(seconds)
+      )
+    );
+  };
+
+  const balance = async (account: PublicKey) =>
+    Number(await context.banksClient.getBalance(account));
+
+  const tokenBalance = async (account: PublicKey) => {
+    const info = await context.banksClient.getAccount(account);
+    return Number(AccountLayout.decode(info.data).amount);
+  };
+
+  // bankrun errors may lack program logs, so match the error code as well.
+  const expectError = async (promise: Promise<unknown>, code: string) => {
+    const number = program.idl.errors.find((e) => e.name === code).code;
+    try {
+      await promise;
+    } catch (err) {
+      const text = err.toString();
+      assert.isTrue(
+        text.includes(code) || text.includes(`0x${number.toString(16)}`),
+        `expected ${code}, got ${text}`
+      );
+      return;
+    }
+    assert.fail(`expected ${code}`);
+  };
+
+  const queueSol = (id: number, amount: BN | null, destination: PublicKey) =>
+    program.methods
+      .queueWithdrawal({
+        withdrawalId: new BN(id),
+        currency: { sol: {} },
+        mint: PublicKey.default,
+        amount,
+        destination,
+      })
+      .accounts({
+        pendingWithdrawal: withdrawalPda(id),
+        game,
+        payer,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+  const executeSol = (id: number, destination: PublicKey) =>
+    program.methods
+      .executeWithdrawalSol()
+      .accounts({
+        pendingWithdrawal: withdrawalPda(id),
+        game,
+        destination,
+        payer,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+  before(async () => {
+    context = await startAnchor("", [], []);
+    const provider = new BankrunProvider(context);
+    program = new Program<LotteryGame>(IDL, PROGRAM_ID, provider);
+    payer = provider.wallet.publicKey;
+
+    const gameId = new BN(1);
+    [game] = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("game"),
+        payer.toBuffer(),
+        gameId.toArrayLike(Buffer, "le", 8),
+      ],
+      PROGRAM_ID
+    );
+    await program.methods
+      .initGame({ gameId, amount: new BN(5 * LAMPORTS_PER_SOL) })
+      .accounts({ game, payer, systemProgram: SystemProgram.programId })
+      .rpc();
+  });
+
+  it("keeps a withdrawal locked until its delay has passed", async () => {
+    const destination = Keypair.generate().publicKey;
+    await queueSol(1, new BN(LAMPORTS_PER_SOL), destination);
+
+    await expectError(executeSol(1, destination), "WithdrawalLocked");
+
+    await warp(DAY);
+    await executeSol(1, destination);
+    assert.equal(await balance(destination), LAMPORTS_PER_SOL);
+    assert.isNull(await context.banksClient.getAccount(withdrawalPda(1)));
+  });
+
+  it("expires a withdrawal left past its window", async () => {
+    const destination = Keypair.generate().publicKey;
+    await queueSol(2, new BN(LAMPORTS_PER_SOL), destination);
+
+    await warp(DAY + WINDOW + 1);
+    await expectError(executeSol(2, destination), "TimelockExpired");
+  });
+
+  it("refuses to withdraw more than the surplus", async () => {
+    const destination = Keypair.generate().publicKey;
+    await queueSol(3, new BN(100 * LAMPORTS_PER_SOL), destination);
+
+    await warp(DAY);
+    await expectError(executeSol(3, destination), "InsufficientSurplus");
+  });
+
+  it("withdraws the whole SOL surplus when no amount is queued", async () => {
+    const destination = Keypair.generate().publicKey;
+    await queueSol(4, null, destination);
+
+    await warp(DAY);
+    await executeSol(4, destination);
+
+    // Only the game's rent-exempt minimum stays behind.
+    const rent = await context.banksClient.getRent();
+    const rentExempt = Number(rent.minimumBalance(BigInt(GAME_SPACE)));
+    assert.equal(await balance(game), rentExempt);
+    assert.equal(await balance(destination), 4 * LAMPORTS_PER_SOL);
+  });
+
+  it("withdraws the whole token surplus when no amount is queued", async () => {
+    const mint = Keypair.generate();
+    const owner = Keypair.generate().publicKey;
+    const payerTokenAccount = getAssociatedTokenAddressSync(
+      mint.publicKey,
+      payer
+    );
+    const destination = getAssociatedTokenAddressSync(mint.publicKey, owner);
+    const gameTokenAccount = getAssociatedTokenAddressSync(
+      mint.publicKey,
+      game,
+      true
+    );
+    const [tokenVault] = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), game.toBuffer(), mint.publicKey.toBuffer()],
+      PROGRAM_ID
+    );
+
+    const rent = await context.banksClient.getRent();
+    const setup = new Transaction().add(
+      SystemProgram.createAccount({
+        fromPubkey: payer,
+        newAccountPubkey: mint.publicKey,
+        space: MINT_SIZE,
+        lamports: Number(rent.minimumBalance(BigInt(MINT_SIZE))),
+        programId: TOKEN_PROGRAM_ID,
+      }),
+      createInitializeMint2Instruction(mint.publicKey, 6, payer, null),
+      createAssociatedTokenAccountIdempotentInstruction(
+        payer,
+        payerTokenAccount,
+        payer,
+        mint.publicKey
+      ),
+      createAssociatedTokenAccountIdempotentInstruction(
+        payer,
+        destination,
+        owner,
+        mint.publicKey
+      ),
+      createMintToInstruction(
+        mint.publicKey,
+        payerTokenAccount,
+        payer,
+        1_000_000_000
+      )
+    );
+    await program.provider.sendAndConfirm(setup, [mint]);
+
+    await program.methods
+      .addToken({ defaultMultiplier: 6, amount: new BN(500_000_000) })
+      .accounts({
+        game,
+        tokenVault,
+        gameTokenAccount,
+        mint: mint.publicKey,
+        payerTokenAccount,
+        payer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    const withdrawal = withdrawalPda(5);
+    await program.methods
+      .queueWithdrawal({
+        withdrawalId: new BN(5),
+        currency: { token: {} },
+        mint: mint.publicKey,
+        amount: null,
+        destination,
+      })
+      .accounts({
+        pendingWithdrawal: withdrawal,
+        game,
+        payer,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    await warp(DAY);
+    await program.methods
+      .executeWithdrawalToken()
+      .accounts({
+        pendingWithdrawal: withdrawal,
+        game,
+        gameTokenAccount,
+        tokenVault,
+        mint: mint.publicKey,
+        destinationTokenAccount: destination,
+        payer,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    assert.equal(await tokenBalance(destination), 500_000_000);
+    assert.equal(await tokenBalance(gameTokenAccount), 0);
+  });
+
+  it("applies a treasury change only inside its window", async () => {
+    const treasurer = Keypair.generate().publicKey;
+    const treasuryChange = treasuryChangePda();
+    const queue = (withdrawalDelay: number) =>
+      program.methods
+        .queueTreasuryChange({
+          treasurer,
+          withdrawalDelay: new BN(withdrawalDelay),
+        })
+        .accounts({
+          treasuryChange,
+          game,
+          payer,
+          systemProgram: SystemProgram.programId,
+        })
+        .rpc();
+    const apply = () =>
+      program.methods
+        .applyTreasuryChange()
+        .accounts({ treasuryChange, game, payer })
+        .rpc();
+
+    await queue(0);
+    await expectError(apply(), "WithdrawalLocked");
+
+    await warp(DAY);
+    await apply();
+    let state = await program.account.game.fetch(game);
+    assert.ok(state.treasurer.equals(treasurer));
+    assert.equal(state.withdrawalDelay.toNumber(), 0);
+
+    // With no delay left the next change unlocks at once, but still expires.
+    await queue(DAY);
+    await warp(WINDOW + 1);
+    await expectError(apply(), "TimelockExpired");
+
+    state = await program.account.game.fetch(game);
+    assert.equal(state.withdrawalDelay.toNumber(), 0);
+  });
+});